@@ -4,11 +4,71 @@ fn main() {
     std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
     let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
 
-    tonic_build::configure()
+    let mut builder = tonic_build::configure();
+    // Stored types are also written as JSON snapshots.
+    for path in [
+        ".news.News",
+        ".posts.Post",
+        ".users.User",
+        ".users.Address",
+        ".users.Geo",
+        ".users.Company",
+        ".snapshot.Snapshot",
+    ] {
+        builder = builder.type_attribute(
+            path,
+            "#[derive(serde::Serialize, serde::Deserialize)] #[serde(default)]",
+        );
+    }
+    // Request-only flags are stripped before storing and never belong in a snapshot.
+    for path in [
+        ".news.News.validate_only",
+        ".posts.Post.validate_only",
+        ".users.User.validate_only",
+    ] {
+        builder = builder.field_attribute(path, "#[serde(skip)]");
+    }
+    builder
         .file_descriptor_set_path(out_dir.join("grpc_descriptor.bin"))
         .compile(
-            &["proto/news.proto", "proto/posts.proto", "proto/users.proto"],
+            &[
+                "proto/events.proto",
+                "proto/meta.proto",
+                "proto/news.proto",
+                "proto/posts.proto",
+                "proto/users.proto",
+                "proto/diagnostics.proto",
+                "proto/snapshot.proto",
+            ],
             &["proto"],
         )
         .unwrap();
+
+    let manifest = std::fs::read_to_string("Cargo.toml").unwrap();
+    println!(
+        "cargo:rustc-env=TONIC_VERSION={}",
+        dependency_version(&manifest, "tonic")
+    );
+    println!(
+        "cargo:rustc-env=OPENTELEMETRY_VERSION={}",
+        dependency_version(&manifest, "opentelemetry")
+    );
+    println!("cargo:rerun-if-changed=Cargo.toml");
+}
+
+/// Reads the version requirement of `name` from the `[dependencies]` table of the manifest.
+fn dependency_version(manifest: &str, name: &str) -> String {
+    manifest
+        .lines()
+        .skip_while(|line| line.trim() != "[dependencies]")
+        .find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            if key.trim() != name {
+                return None;
+            }
+            let start = value.find('"')? + 1;
+            let end = start + value[start..].find('"')?;
+            Some(value[start..end].to_string())
+        })
+        .unwrap_or_else(|| "unknown".into())
 }
@@ -0,0 +1,26 @@
+//! Append-only record of who changed what, kept apart from the content change log.
+//!
+//! Entries are only ever appended; there is no API to edit or remove them.
+
+use std::sync::{Arc, Mutex};
+
+use crate::grpc::diagnostics::AuditEntry;
+
+#[derive(Debug, Clone, Default)]
+pub struct AuditLog {
+    entries: Arc<Mutex<Vec<AuditEntry>>>,
+}
+
+impl AuditLog {
+    /// Appends `entry`, overwriting its sequence with its 1-based position in the log.
+    pub fn append(&self, mut entry: AuditEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        entry.sequence = entries.len() as u64 + 1;
+        entries.push(entry);
+    }
+
+    /// Every entry, oldest first.
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,64 @@
+//! Broadcast feeds of store mutations with gap-free sequence numbers.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::broadcast;
+
+/// Number of events buffered per subscriber before it is reported as lagged.
+const FEED_BUFFER: usize = 1024;
+
+/// A broadcast channel whose events carry a sequence number increasing by one per event.
+#[derive(Debug, Clone)]
+pub struct ChangeFeed<T> {
+    /// Sequence number of the last published event. Held while sending so that channel order
+    /// always matches sequence order.
+    sequence: Arc<Mutex<u64>>,
+    sender: broadcast::Sender<T>,
+    /// Most recent events, newest first, kept for late readers.
+    retained: Arc<Mutex<VecDeque<T>>>,
+    retention: usize,
+}
+
+impl<T: Clone> ChangeFeed<T> {
+    pub fn new() -> Self {
+        Self::with_retention(0)
+    }
+
+    /// Creates a feed that also keeps the last `retention` events for [`ChangeFeed::recent`].
+    pub fn with_retention(retention: usize) -> Self {
+        let (sender, _) = broadcast::channel(FEED_BUFFER);
+        Self {
+            sequence: Arc::default(),
+            sender,
+            retained: Arc::default(),
+            retention,
+        }
+    }
+
+    /// Assigns the next sequence number and publishes the event built from it.
+    pub fn publish(&self, event: impl FnOnce(u64) -> T) -> u64 {
+        let mut sequence = self.sequence.lock().unwrap();
+        *sequence += 1;
+        let event = event(*sequence);
+        if self.retention > 0 {
+            let mut retained = self.retained.lock().unwrap();
+            retained.push_front(event.clone());
+            retained.truncate(self.retention);
+        }
+        // Sending only fails when nobody is subscribed, which is fine.
+        let _ = self.sender.send(event);
+        *sequence
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<T> {
+        self.sender.subscribe()
+    }
+
+    /// Retained events, newest first.
+    pub fn recent(&self) -> Vec<T> {
+        self.retained.lock().unwrap().iter().cloned().collect()
+    }
+}
@@ -0,0 +1,197 @@
+//! Consecutive-failure circuit breaker guarding the storage backend.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Externally visible breaker state.
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Calls pass; consecutive failures are counted.
+    Closed,
+    /// Calls are rejected until the reset interval has passed.
+    Open,
+    /// A single probe call is let through to decide whether to close again.
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum State {
+    Closed { failures: u32 },
+    Open { since: Instant },
+    HalfOpen { probing: bool },
+}
+
+/// Opens after `threshold` consecutive failures and lets one probe through once `reset` has
+/// passed: its success closes the breaker, its failure opens it again.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    reset: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, reset: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            reset,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    /// Admits a call at `now`, or returns `None` while the breaker is open or a probe is
+    /// already in flight. The call's outcome is reported through the returned permit.
+    pub fn acquire(self: &Arc<Self>, now: Instant) -> Option<Permit> {
+        let mut state = self.state.lock().unwrap();
+        let probe = match *state {
+            State::Closed { .. } => false,
+            State::Open { since } if now.duration_since(since) >= self.reset => {
+                *state = State::HalfOpen { probing: true };
+                true
+            }
+            State::Open { .. } => return None,
+            State::HalfOpen { ref mut probing } if !*probing => {
+                *probing = true;
+                true
+            }
+            State::HalfOpen { .. } => return None,
+        };
+        Some(Permit {
+            breaker: self.clone(),
+            probe,
+            finished: false,
+        })
+    }
+
+    fn record(&self, success: bool, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        *state = match (&*state, success) {
+            (State::Open { since }, _) => State::Open { since: *since },
+            (_, true) => State::Closed { failures: 0 },
+            (State::Closed { failures }, false) if failures + 1 < self.threshold => State::Closed {
+                failures: failures + 1,
+            },
+            (_, false) => State::Open { since: now },
+        };
+    }
+
+    /// State as of `now`; an open breaker past its reset interval reports half-open.
+    #[cfg(test)]
+    pub fn state(&self, now: Instant) -> BreakerState {
+        match *self.state.lock().unwrap() {
+            State::Closed { .. } => BreakerState::Closed,
+            State::Open { since } if now.duration_since(since) >= self.reset => {
+                BreakerState::HalfOpen
+            }
+            State::Open { .. } => BreakerState::Open,
+            State::HalfOpen { .. } => BreakerState::HalfOpen,
+        }
+    }
+}
+
+/// An admitted call. Dropping a probe's permit without [`finish`]ing it, as happens when the
+/// call times out or the client cancels it, counts as a failed probe so the breaker reopens
+/// and admits another probe later instead of waiting on this one forever.
+///
+/// [`finish`]: Self::finish
+#[derive(Debug)]
+pub struct Permit {
+    breaker: Arc<CircuitBreaker>,
+    probe: bool,
+    finished: bool,
+}
+
+impl Permit {
+    pub fn finish(mut self, success: bool, now: Instant) {
+        self.finished = true;
+        self.breaker.record(success, now);
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if !self.finished && self.probe {
+            self.breaker.record(false, Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(threshold: u32) -> Arc<CircuitBreaker> {
+        Arc::new(CircuitBreaker::new(threshold, Duration::from_secs(10)))
+    }
+
+    fn fail(breaker: &Arc<CircuitBreaker>, now: Instant) {
+        breaker.acquire(now).unwrap().finish(false, now);
+    }
+
+    #[test]
+    fn opens_after_threshold_and_closes_after_successful_probe() {
+        let breaker = breaker(2);
+        let start = Instant::now();
+
+        fail(&breaker, start);
+        assert_eq!(breaker.state(start), BreakerState::Closed);
+        fail(&breaker, start);
+        assert_eq!(breaker.state(start), BreakerState::Open);
+        assert!(breaker.acquire(start + Duration::from_secs(9)).is_none());
+
+        let later = start + Duration::from_secs(10);
+        let probe = breaker.acquire(later).unwrap();
+        assert!(breaker.acquire(later).is_none(), "only one probe at a time");
+        probe.finish(true, later);
+        assert_eq!(breaker.state(later), BreakerState::Closed);
+    }
+
+    #[test]
+    fn failed_probe_reopens() {
+        let breaker = breaker(1);
+        let start = Instant::now();
+        fail(&breaker, start);
+
+        let later = start + Duration::from_secs(10);
+        fail(&breaker, later);
+
+        assert_eq!(breaker.state(later), BreakerState::Open);
+        assert!(breaker.acquire(later + Duration::from_secs(5)).is_none());
+    }
+
+    #[test]
+    fn dropped_probe_reopens_and_a_later_probe_is_admitted() {
+        let breaker = Arc::new(CircuitBreaker::new(1, Duration::ZERO));
+        let now = Instant::now();
+        fail(&breaker, now);
+
+        drop(breaker.acquire(now).unwrap());
+
+        assert!(breaker.acquire(Instant::now()).is_some());
+    }
+
+    #[test]
+    fn dropped_call_while_closed_is_not_a_failure() {
+        let breaker = breaker(1);
+        let now = Instant::now();
+
+        drop(breaker.acquire(now).unwrap());
+
+        assert_eq!(breaker.state(now), BreakerState::Closed);
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let breaker = breaker(2);
+        let now = Instant::now();
+
+        fail(&breaker, now);
+        breaker.acquire(now).unwrap().finish(true, now);
+        fail(&breaker, now);
+
+        assert_eq!(breaker.state(now), BreakerState::Closed);
+    }
+}
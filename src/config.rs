@@ -0,0 +1,600 @@
+//! Runtime configuration resolved from environment variables at startup.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    net::IpAddr,
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use hyper::header::HeaderName;
+use semver::Version;
+use tonic::codec::CompressionEncoding;
+
+use crate::middleware::Role;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Page size used when a paginated request leaves `page_size` unset.
+    pub default_page_size: usize,
+    /// Upper bound for `page_size`; larger requests are clamped rather than rejected.
+    pub max_page_size: usize,
+    /// Maximum size in bytes of each chunk streamed by `GetPostBody`.
+    pub post_body_chunk_bytes: usize,
+    /// Gzip-compresses responses for clients that accept it.
+    pub compression: bool,
+    /// Response messages smaller than this are sent uncompressed even when compression is on.
+    pub compression_min_bytes: usize,
+    /// Message encodings tonic accepts and, when the client's `grpc-accept-encoding` lists
+    /// them, responds with, from `COMPRESSION_ALGOS=gzip,zstd`. Responses compressed this way
+    /// are left alone by the `COMPRESSION` layer.
+    pub compression_algos: Vec<CompressionEncoding>,
+    /// Requests whose encoded messages add up to more than this many bytes are rejected with
+    /// `resource_exhausted`.
+    pub request_bytes_soft_limit: usize,
+    /// Longest news or post `body`, in bytes, accepted by creates and updates without a
+    /// limit of their own; unlimited when unset.
+    pub max_body: Option<usize>,
+    /// Longest `body` accepted by `AddNews` and `CreatePost`; falls back to `max_body`.
+    pub max_create_body: Option<usize>,
+    /// Longest `body` accepted by `EditNews` and `UpdatePost`; falls back to `max_body`.
+    pub max_update_body: Option<usize>,
+    /// Requests carrying more metadata entries than this are rejected with `invalid_argument`.
+    pub max_metadata_entries: usize,
+    /// Requests whose metadata names and values total more bytes than this are rejected.
+    pub max_metadata_bytes: usize,
+    /// Masks emails and phone numbers in request logs.
+    pub log_redact: bool,
+    /// Header carrying the request id, read from requests and echoed on responses.
+    pub request_id_header: HeaderName,
+    /// File receiving one JSON line per request; no access log is written when unset.
+    pub access_log_path: Option<PathBuf>,
+    /// Peer addresses whose incoming trace context is honored, from
+    /// `TRUSTED_TRACE_PEERS=10.0.0.1,10.0.0.2`. Other peers start a fresh trace; unset trusts
+    /// every peer.
+    pub trusted_trace_peers: Option<HashSet<IpAddr>>,
+    /// `GetUserLoginHint` calls allowed per peer address per minute.
+    pub login_hint_rate_limit: u32,
+    /// Company given to users created without one, from `DEFAULT_COMPANY_NAME`.
+    pub default_company_name: Option<String>,
+    /// API key to client id, from `API_KEYS=key1=client1,key2=client2`. Authentication is
+    /// disabled when empty, and admin-only RPCs are then denied to everyone.
+    pub api_keys: HashMap<String, String>,
+    /// API key to role, from `API_KEY_ROLES=key1=reader,key2=admin`. Keys without a role are
+    /// readers.
+    pub api_key_roles: HashMap<String, Role>,
+    /// Oldest `x-client-version` accepted; every client is accepted when unset.
+    pub min_client_version: Option<Version>,
+    /// Whether requests without `x-client-version` pass the minimum version check.
+    pub allow_missing_client_version: bool,
+    /// Schema version assumed for requests without `x-schema-version`.
+    pub default_schema_version: u32,
+    /// Client id to the user it acts as, from `CLIENT_USERS=client1=1,client2=2`. Used for
+    /// ownership checks such as `TransferPost`.
+    pub client_users: HashMap<String, i32>,
+    /// Read-replica mode: every mutating RPC fails with `failed_precondition`.
+    pub read_only: bool,
+    /// Window within which `WatchNews` events are batched into one message; unbatched when unset.
+    pub watch_batch: Option<Duration>,
+    /// Number of prior versions kept per news item.
+    pub history_depth: usize,
+    /// Number of recent change events retained for `GetUserActivity`, and of mutations that
+    /// `UndoLastChange` can revert.
+    pub change_log_capacity: usize,
+    /// Records `store_lock_wait_ms` whenever a store lock is contended.
+    pub store_lock_metrics: bool,
+    /// Maximum number of entities per store; unbounded when unset.
+    pub max_entities: Option<usize>,
+    /// Rejects sensitive RPCs (user edits, admin methods) received over plaintext.
+    pub require_tls: bool,
+    /// Whether grpc-web requests (HTTP/1.x, or carrying `x-grpc-web`) without an
+    /// `application/grpc-web*` content-type are rejected with `invalid_argument`.
+    pub enforce_grpc_web_content_type: bool,
+    /// Maximum number of RPCs handled at once; further requests queue. Unlimited when unset.
+    pub max_concurrent_requests: Option<usize>,
+    /// Turns handler panics into `internal` statuses at the cost of a task spawn per request.
+    pub catch_panics: bool,
+    /// Simultaneous TCP connections accepted; further connections are closed. Unlimited when
+    /// unset.
+    pub max_connections: Option<usize>,
+    /// Simultaneously open `WatchNews` and `WatchAllChanges` streams, together; further
+    /// subscriptions fail with `resource_exhausted`. Unlimited when unset.
+    pub max_watchers: Option<usize>,
+    /// Interval between store health probes.
+    pub health_check_interval: Duration,
+    /// Consecutive storage failures that open the circuit breaker, after which RPCs fail with
+    /// `unavailable`; no breaker when unset.
+    pub cb_failure_threshold: Option<u32>,
+    /// How long an open circuit breaker waits before letting a probe RPC through.
+    pub cb_reset: Duration,
+    /// How long news with status `DELETED` is kept before a background task purges it, from
+    /// `SOFT_DELETE_RETENTION_DAYS`; never purged when unset. News is the only entity with a
+    /// soft-deleted state, and `DeleteNews` removes items outright, so this only applies to
+    /// items written with that status, e.g. through `AddNews`, a snapshot or synthetic data.
+    pub soft_delete_retention: Option<Duration>,
+    /// Consecutive failed probes before health reports `NotServing`.
+    pub health_failure_threshold: u32,
+    /// How long read responses such as `GetAllNews` are served from memory, unless the entities
+    /// they read change first; no caching when unset.
+    pub read_cache_ttl: Option<Duration>,
+    /// Handler timeout for methods without an entry in `method_timeouts`; none when unset.
+    pub request_timeout: Option<Duration>,
+    /// Handler timeouts by full method path or bare method name, from
+    /// `METHOD_TIMEOUTS=GetAllNews=500,/news.NewsService/WatchNews=60000` in milliseconds.
+    pub method_timeouts: HashMap<String, Duration>,
+    /// How long shutdown waits for in-flight RPCs to finish before terminating them.
+    pub shutdown_timeout: Duration,
+    /// How long health checks report `NotServing` after a shutdown request before the server
+    /// stops accepting requests, so load balancers stop routing to it first.
+    pub shutdown_health_grace: Duration,
+    /// Accepts posts whose `user_id` matches no stored user.
+    pub skip_author_check: bool,
+    /// Rejects create requests carrying server-assigned fields, such as a nonzero `id`, instead
+    /// of silently overwriting them.
+    pub strict_mode: bool,
+    /// Refuses to start unless the compiled descriptor set declares every served service.
+    pub startup_selftest: bool,
+    /// Builds the service and its layers, then returns before binding the socket, so CI can
+    /// check a deployment would start without occupying a port.
+    pub dry_run: bool,
+    /// Injects random latency into a fraction of requests, for testing client timeouts.
+    pub chaos_mode: bool,
+    /// Fraction of requests, between 0 and 1, delayed in chaos mode.
+    pub chaos_fraction: f64,
+    /// Upper bound of the latency injected in chaos mode.
+    pub chaos_max_latency: Duration,
+    /// Replaces the built-in fixtures with this many generated users, posts and news items,
+    /// the same on every run, for load testing.
+    pub seed_count: Option<usize>,
+    /// Where entities are stored, from `STORAGE_BACKEND`. `memory` is the only backend so far;
+    /// durable ones are added as variants of [`StorageBackend`].
+    pub storage_backend: StorageBackend,
+    /// File the stores are restored from at startup, if it exists, and saved to after a
+    /// graceful shutdown. Nothing is persisted when unset.
+    pub snapshot_path: Option<PathBuf>,
+    /// Encoding of the snapshot file, from `SNAPSHOT_FORMAT=json|proto`.
+    pub snapshot_format: SnapshotFormat,
+}
+
+/// Storage implementation selected at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageBackend {
+    /// Seeded, process-local vectors; contents are lost on restart.
+    #[default]
+    Memory,
+}
+
+/// Serializer used for snapshot files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnapshotFormat {
+    /// Human-readable JSON.
+    #[default]
+    Json,
+    /// Compact protobuf binary.
+    Proto,
+}
+
+impl FromStr for SnapshotFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "json" => Ok(Self::Json),
+            "proto" => Ok(Self::Proto),
+            other => Err(format!("unknown snapshot format `{other}`")),
+        }
+    }
+}
+
+impl FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "memory" => Ok(Self::Memory),
+            other => Err(format!("unknown storage backend `{other}`")),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            default_page_size: 50,
+            max_page_size: 500,
+            post_body_chunk_bytes: 4 * 1024,
+            request_bytes_soft_limit: 4 * 1024 * 1024,
+            max_body: None,
+            max_create_body: None,
+            max_update_body: None,
+            max_metadata_entries: 64,
+            max_metadata_bytes: 8 * 1024,
+            compression: false,
+            compression_min_bytes: 1024,
+            compression_algos: Vec::new(),
+            log_redact: false,
+            request_id_header: HeaderName::from_static("x-request-id"),
+            access_log_path: None,
+            trusted_trace_peers: None,
+            login_hint_rate_limit: 10,
+            default_company_name: None,
+            api_keys: HashMap::new(),
+            api_key_roles: HashMap::new(),
+            client_users: HashMap::new(),
+            min_client_version: None,
+            allow_missing_client_version: true,
+            default_schema_version: 1,
+            read_only: false,
+            watch_batch: None,
+            history_depth: 10,
+            change_log_capacity: 1000,
+            store_lock_metrics: true,
+            max_entities: None,
+            require_tls: false,
+            enforce_grpc_web_content_type: true,
+            max_concurrent_requests: None,
+            catch_panics: true,
+            max_connections: None,
+            max_watchers: None,
+            health_check_interval: Duration::from_secs(5),
+            cb_failure_threshold: None,
+            cb_reset: Duration::from_secs(30),
+            soft_delete_retention: None,
+            health_failure_threshold: 3,
+            read_cache_ttl: None,
+            request_timeout: None,
+            method_timeouts: HashMap::new(),
+            shutdown_timeout: Duration::from_secs(30),
+            shutdown_health_grace: Duration::ZERO,
+            skip_author_check: false,
+            strict_mode: false,
+            startup_selftest: false,
+            dry_run: false,
+            chaos_mode: false,
+            chaos_fraction: 0.1,
+            chaos_max_latency: Duration::from_secs(1),
+            seed_count: None,
+            storage_backend: StorageBackend::default(),
+            snapshot_path: None,
+            snapshot_format: SnapshotFormat::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self> {
+        let defaults = Self::default();
+        Ok(Self {
+            default_page_size: env_or("DEFAULT_PAGE_SIZE", defaults.default_page_size)?,
+            max_page_size: env_or("MAX_PAGE_SIZE", defaults.max_page_size)?,
+            post_body_chunk_bytes: env_or("POST_BODY_CHUNK_BYTES", defaults.post_body_chunk_bytes)?,
+            max_body: env_opt("MAX_BODY")?.or(defaults.max_body),
+            max_create_body: env_opt("MAX_CREATE_BODY")?.or(defaults.max_create_body),
+            max_update_body: env_opt("MAX_UPDATE_BODY")?.or(defaults.max_update_body),
+            request_bytes_soft_limit: env_or(
+                "REQUEST_BYTES_SOFT_LIMIT",
+                defaults.request_bytes_soft_limit,
+            )?,
+            max_metadata_entries: env_or("MAX_METADATA_ENTRIES", defaults.max_metadata_entries)?,
+            max_metadata_bytes: env_or("MAX_METADATA_BYTES", defaults.max_metadata_bytes)?,
+            compression: env_flag("COMPRESSION", defaults.compression)?,
+            compression_min_bytes: env_or("COMPRESSION_MIN_BYTES", defaults.compression_min_bytes)?,
+            compression_algos: match std::env::var("COMPRESSION_ALGOS") {
+                Ok(value) => parse_compression_algos(&value)?,
+                Err(_) => defaults.compression_algos,
+            },
+            log_redact: env_flag("LOG_REDACT", defaults.log_redact)?,
+            request_id_header: env_or("REQUEST_ID_HEADER", defaults.request_id_header)?,
+            access_log_path: env_opt("ACCESS_LOG_PATH")?.or(defaults.access_log_path),
+            trusted_trace_peers: match std::env::var("TRUSTED_TRACE_PEERS") {
+                Ok(value) => Some(
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|peer| !peer.is_empty())
+                        .map(|peer| {
+                            peer.parse::<IpAddr>()
+                                .map_err(|e| anyhow!("invalid value for TRUSTED_TRACE_PEERS: {e}"))
+                        })
+                        .collect::<Result<HashSet<_>>>()?,
+                ),
+                Err(_) => defaults.trusted_trace_peers,
+            },
+            login_hint_rate_limit: env_or("LOGIN_HINT_RATE_LIMIT", defaults.login_hint_rate_limit)?,
+            default_company_name: env_opt("DEFAULT_COMPANY_NAME")?
+                .or(defaults.default_company_name),
+            api_keys: match std::env::var("API_KEYS") {
+                Ok(value) => parse_key_values("API_KEYS", &value)?,
+                Err(_) => defaults.api_keys,
+            },
+            api_key_roles: match std::env::var("API_KEY_ROLES") {
+                Ok(value) => parse_key_values("API_KEY_ROLES", &value)?
+                    .into_iter()
+                    .map(|(key, role)| {
+                        let role = role
+                            .parse::<Role>()
+                            .map_err(|e| anyhow!("invalid value for API_KEY_ROLES: {e}"))?;
+                        Ok((key, role))
+                    })
+                    .collect::<Result<HashMap<_, _>>>()?,
+                Err(_) => defaults.api_key_roles,
+            },
+            client_users: match std::env::var("CLIENT_USERS") {
+                Ok(value) => parse_key_values("CLIENT_USERS", &value)?
+                    .into_iter()
+                    .map(|(client, user)| {
+                        let user = user
+                            .parse::<i32>()
+                            .map_err(|e| anyhow!("invalid value for CLIENT_USERS: {e}"))?;
+                        Ok((client, user))
+                    })
+                    .collect::<Result<HashMap<_, _>>>()?,
+                Err(_) => defaults.client_users,
+            },
+            min_client_version: env_opt("MIN_CLIENT_VERSION")?.or(defaults.min_client_version),
+            allow_missing_client_version: env_flag(
+                "ALLOW_MISSING_CLIENT_VERSION",
+                defaults.allow_missing_client_version,
+            )?,
+            default_schema_version: env_or(
+                "DEFAULT_SCHEMA_VERSION",
+                defaults.default_schema_version,
+            )?,
+            read_only: env_flag("READ_ONLY", defaults.read_only)?,
+            watch_batch: env_opt("WATCH_BATCH_MS")?
+                .map(Duration::from_millis)
+                .or(defaults.watch_batch),
+            history_depth: env_or("HISTORY_DEPTH", defaults.history_depth)?,
+            change_log_capacity: env_or("CHANGE_LOG_CAPACITY", defaults.change_log_capacity)?,
+            store_lock_metrics: env_flag("STORE_LOCK_METRICS", defaults.store_lock_metrics)?,
+            max_entities: env_opt("MAX_ENTITIES")?.or(defaults.max_entities),
+            require_tls: env_flag("REQUIRE_TLS", defaults.require_tls)?,
+            enforce_grpc_web_content_type: env_flag(
+                "ENFORCE_GRPC_WEB_CONTENT_TYPE",
+                defaults.enforce_grpc_web_content_type,
+            )?,
+            max_concurrent_requests: env_opt("MAX_CONCURRENT_REQUESTS")?
+                .or(defaults.max_concurrent_requests),
+            catch_panics: env_flag("CATCH_PANICS", defaults.catch_panics)?,
+            max_connections: env_opt("MAX_CONNECTIONS")?.or(defaults.max_connections),
+            max_watchers: env_opt("MAX_WATCHERS")?.or(defaults.max_watchers),
+            health_check_interval: env_opt("HEALTH_CHECK_INTERVAL_MS")?
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.health_check_interval),
+            cb_failure_threshold: env_opt("CB_FAILURE_THRESHOLD")?
+                .or(defaults.cb_failure_threshold),
+            cb_reset: env_opt("CB_RESET_MS")?
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.cb_reset),
+            soft_delete_retention: env_opt("SOFT_DELETE_RETENTION_DAYS")?
+                .map(|days: u64| Duration::from_secs(days * 24 * 60 * 60))
+                .or(defaults.soft_delete_retention),
+            health_failure_threshold: env_or(
+                "HEALTH_FAILURE_THRESHOLD",
+                defaults.health_failure_threshold,
+            )?,
+            read_cache_ttl: env_opt("READ_CACHE_TTL_MS")?
+                .map(Duration::from_millis)
+                .or(defaults.read_cache_ttl),
+            request_timeout: env_opt("REQUEST_TIMEOUT_MS")?
+                .map(Duration::from_millis)
+                .or(defaults.request_timeout),
+            method_timeouts: match std::env::var("METHOD_TIMEOUTS") {
+                Ok(value) => parse_key_values("METHOD_TIMEOUTS", &value)?
+                    .into_iter()
+                    .map(|(method, millis)| {
+                        let millis = millis
+                            .parse::<u64>()
+                            .map_err(|e| anyhow!("invalid value for METHOD_TIMEOUTS: {e}"))?;
+                        Ok((method, Duration::from_millis(millis)))
+                    })
+                    .collect::<Result<HashMap<_, _>>>()?,
+                Err(_) => defaults.method_timeouts,
+            },
+            shutdown_timeout: env_opt("SHUTDOWN_TIMEOUT_MS")?
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.shutdown_timeout),
+            shutdown_health_grace: env_opt("SHUTDOWN_HEALTH_GRACE_MS")?
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.shutdown_health_grace),
+            skip_author_check: env_flag("SKIP_AUTHOR_CHECK", defaults.skip_author_check)?,
+            strict_mode: env_flag("STRICT_MODE", defaults.strict_mode)?,
+            startup_selftest: env_flag("STARTUP_SELFTEST", defaults.startup_selftest)?,
+            dry_run: env_flag("DRY_RUN", defaults.dry_run)?,
+            chaos_mode: env_flag("CHAOS_MODE", defaults.chaos_mode)?,
+            chaos_fraction: env_or("CHAOS_FRACTION", defaults.chaos_fraction)?,
+            chaos_max_latency: env_opt("CHAOS_MAX_LATENCY_MS")?
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.chaos_max_latency),
+            seed_count: env_opt("SEED_COUNT")?.or(defaults.seed_count),
+            storage_backend: env_or("STORAGE_BACKEND", defaults.storage_backend)?,
+            snapshot_path: env_opt("SNAPSHOT_PATH")?.or(defaults.snapshot_path),
+            snapshot_format: env_or("SNAPSHOT_FORMAT", defaults.snapshot_format)?,
+        })
+    }
+
+    /// Body length limit for creates, in bytes.
+    pub fn create_body_limit(&self) -> Option<usize> {
+        self.max_create_body.or(self.max_body)
+    }
+
+    /// Body length limit for updates, in bytes.
+    pub fn update_body_limit(&self) -> Option<usize> {
+        self.max_update_body.or(self.max_body)
+    }
+
+    /// Resolves the effective page size for a request: `0` means "use the default", and
+    /// anything above the maximum is clamped to it.
+    pub fn page_size(&self, requested: u32) -> usize {
+        let size = match requested {
+            0 => self.default_page_size,
+            n => n as usize,
+        };
+        size.clamp(1, self.max_page_size.max(1))
+    }
+
+    /// Like [`Config::page_size`], but `None` when the request sets neither a page size nor a
+    /// page token. ListPosts and ListUsers returned every item before they paginated, so such
+    /// requests still get the full list instead of a silently truncated first page.
+    pub fn opt_in_page_size(&self, requested: u32, page_token: &str) -> Option<usize> {
+        (requested != 0 || !page_token.is_empty()).then(|| self.page_size(requested))
+    }
+}
+
+/// Parses the environment variable `name`, falling back to `default` when it is unset.
+fn env_or<T>(name: &str, default: T) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|e| anyhow!("invalid value for {name}: {e}")),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Parses the optional environment variable `name`, returning `None` when it is unset.
+fn env_opt<T>(name: &str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    std::env::var(name)
+        .ok()
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|e| anyhow!("invalid value for {name}: {e}"))
+        })
+        .transpose()
+}
+
+/// Reads a boolean flag such as `LOG_REDACT=1`, falling back to `default` when it is unset.
+fn env_flag(name: &str, default: bool) -> Result<bool> {
+    match std::env::var(name) {
+        Ok(value) => match value.as_str() {
+            "1" | "true" => Ok(true),
+            "0" | "false" => Ok(false),
+            _ => Err(anyhow!(
+                "invalid value for {name}: expected 0/1 or true/false"
+            )),
+        },
+        Err(_) => Ok(default),
+    }
+}
+
+/// Parses a comma-separated list of encodings such as `gzip,zstd`, ignoring duplicates.
+fn parse_compression_algos(value: &str) -> Result<Vec<CompressionEncoding>> {
+    let mut algos = Vec::new();
+    for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let algo = match name {
+            "gzip" => CompressionEncoding::Gzip,
+            "zstd" => CompressionEncoding::Zstd,
+            other => {
+                return Err(anyhow!(
+                    "invalid value for COMPRESSION_ALGOS: unknown encoding `{other}`"
+                ))
+            }
+        };
+        if !algos.contains(&algo) {
+            algos.push(algo);
+        }
+    }
+    Ok(algos)
+}
+
+/// Parses a comma-separated list of `key=value` pairs.
+fn parse_key_values(name: &str, value: &str) -> Result<HashMap<String, String>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("invalid entry in {name}: expected key=value"))?;
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_size_defaults_when_unset() {
+        let config = Config::default();
+        assert_eq!(config.page_size(0), config.default_page_size);
+    }
+
+    #[test]
+    fn page_size_is_clamped_to_max() {
+        let config = Config {
+            default_page_size: 10,
+            max_page_size: 20,
+            ..Config::default()
+        };
+        assert_eq!(config.page_size(5), 5);
+        assert_eq!(config.page_size(1000), 20);
+    }
+
+    #[test]
+    fn parses_key_value_lists() {
+        let keys = parse_key_values("API_KEYS", "abc=client-a, def=client-b,").unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys["def"], "client-b");
+        assert!(parse_key_values("API_KEYS", "missing-separator").is_err());
+    }
+
+    #[test]
+    fn parses_storage_backend() {
+        assert_eq!("memory".parse(), Ok(StorageBackend::Memory));
+        assert!("postgres".parse::<StorageBackend>().is_err());
+    }
+
+    #[test]
+    fn parses_compression_algos() {
+        assert_eq!(
+            parse_compression_algos("zstd, gzip,zstd").unwrap(),
+            vec![CompressionEncoding::Zstd, CompressionEncoding::Gzip]
+        );
+        assert!(parse_compression_algos("brotli").is_err());
+    }
+
+    #[test]
+    fn parses_snapshot_format() {
+        assert_eq!("json".parse(), Ok(SnapshotFormat::Json));
+        assert_eq!("proto".parse(), Ok(SnapshotFormat::Proto));
+        assert!("yaml".parse::<SnapshotFormat>().is_err());
+    }
+
+    #[test]
+    fn body_limits_fall_back_to_shared_default() {
+        let config = Config {
+            max_body: Some(100),
+            max_create_body: Some(10),
+            ..Config::default()
+        };
+
+        assert_eq!(config.create_body_limit(), Some(10));
+        assert_eq!(config.update_body_limit(), Some(100));
+        assert_eq!(Config::default().update_body_limit(), None);
+    }
+
+    #[test]
+    fn opt_in_page_size_is_none_only_without_size_or_token() {
+        let config = Config {
+            default_page_size: 10,
+            ..Config::default()
+        };
+        assert_eq!(config.opt_in_page_size(0, ""), None);
+        assert_eq!(config.opt_in_page_size(5, ""), Some(5));
+        assert_eq!(config.opt_in_page_size(0, "3"), Some(10));
+    }
+}
@@ -0,0 +1,163 @@
+//! Caps simultaneous TCP connections at `MAX_CONNECTIONS` to protect file descriptor limits.
+//!
+//! Connections accepted while the cap is reached are closed immediately, before any bytes are
+//! read, so clients see a reset rather than a queued handshake.
+
+use std::{
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use hyper::server::{
+    accept::Accept,
+    conn::{AddrIncoming, AddrStream},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    sync::{OwnedSemaphorePermit, Semaphore},
+};
+
+pub struct LimitedIncoming {
+    inner: AddrIncoming,
+    /// Unlimited when `None`.
+    permits: Option<Arc<Semaphore>>,
+}
+
+impl LimitedIncoming {
+    pub fn new(inner: AddrIncoming, max_connections: Option<usize>) -> Self {
+        Self {
+            inner,
+            permits: max_connections.map(|max| Arc::new(Semaphore::new(max))),
+        }
+    }
+}
+
+impl Accept for LimitedIncoming {
+    type Conn = LimitedConn;
+    type Error = io::Error;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        let this = self.get_mut();
+        loop {
+            let stream = match ready!(Pin::new(&mut this.inner).poll_accept(cx)) {
+                Some(Ok(stream)) => stream,
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => return Poll::Ready(None),
+            };
+            let permit = match &this.permits {
+                None => None,
+                Some(permits) => match permits.clone().try_acquire_owned() {
+                    Ok(permit) => Some(permit),
+                    Err(_) => {
+                        tracing::warn!(
+                            peer = %stream.remote_addr(),
+                            "rejecting connection, MAX_CONNECTIONS reached"
+                        );
+                        continue;
+                    }
+                },
+            };
+            return Poll::Ready(Some(Ok(LimitedConn {
+                stream,
+                _permit: permit,
+            })));
+        }
+    }
+}
+
+/// An accepted connection, holding its slot until dropped.
+pub struct LimitedConn {
+    stream: AddrStream,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl LimitedConn {
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.stream.remote_addr()
+    }
+}
+
+impl AsyncRead for LimitedConn {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for LimitedConn {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{io::AsyncReadExt, net::TcpStream};
+
+    use super::*;
+
+    async fn accept(incoming: &mut LimitedIncoming) -> Option<LimitedConn> {
+        let next = std::future::poll_fn(|cx| Pin::new(&mut *incoming).poll_accept(cx));
+        tokio::time::timeout(Duration::from_millis(100), next)
+            .await
+            .ok()
+            .flatten()
+            .map(Result::unwrap)
+    }
+
+    #[tokio::test]
+    async fn rejects_connections_beyond_the_limit() {
+        let inner = AddrIncoming::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = inner.local_addr();
+        let mut incoming = LimitedIncoming::new(inner, Some(1));
+
+        let _first = TcpStream::connect(addr).await.unwrap();
+        let accepted = accept(&mut incoming)
+            .await
+            .expect("first connection accepted");
+
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        assert!(accept(&mut incoming).await.is_none());
+        let mut buf = [0; 1];
+        assert_eq!(second.read(&mut buf).await.unwrap_or(0), 0);
+
+        drop(accepted);
+        let _third = TcpStream::connect(addr).await.unwrap();
+        assert!(accept(&mut incoming).await.is_some());
+    }
+}
@@ -0,0 +1,168 @@
+//! Serving status published through the standard `grpc.health.v1.Health` service, and the
+//! debouncing of store health probes so a single transient failure doesn't flip it.
+
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use tokio::sync::watch;
+use tokio_stream::{wrappers::WatchStream, Stream, StreamExt};
+use tonic::{Request, Response, Status};
+use tonic_health::pb::{
+    health_check_response::ServingStatus,
+    health_server::{Health, HealthServer},
+    HealthCheckRequest, HealthCheckResponse,
+};
+
+/// Startup readiness and store health, shared by startup, the store probe and shutdown, and
+/// served as `grpc.health.v1.Health`. Every service shares the same stores, so only the whole
+/// server (the `""` service) is reported.
+#[derive(Debug, Clone)]
+pub struct HealthState {
+    /// Set once startup has finished loading data.
+    ready: Arc<AtomicBool>,
+    /// Cleared by the store probe after `HEALTH_FAILURE_THRESHOLD` consecutive failures.
+    healthy: Arc<AtomicBool>,
+    /// Status derived from both flags, followed by `Watch` streams.
+    status: Arc<watch::Sender<ServingStatus>>,
+}
+
+impl Default for HealthState {
+    fn default() -> Self {
+        Self {
+            ready: Arc::default(),
+            healthy: Arc::new(AtomicBool::new(true)),
+            status: Arc::new(watch::channel(ServingStatus::NotServing).0),
+        }
+    }
+}
+
+impl HealthState {
+    #[cfg(test)]
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::SeqCst)
+    }
+
+    /// Records startup or shutdown progress.
+    pub fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::SeqCst);
+        self.publish();
+    }
+
+    /// Records the debounced store probe result, returning whether it changed.
+    pub fn set_healthy(&self, healthy: bool) -> bool {
+        let changed = self.healthy.swap(healthy, Ordering::SeqCst) != healthy;
+        self.publish();
+        changed
+    }
+
+    fn publish(&self) {
+        let status = if self.ready.load(Ordering::SeqCst) && self.healthy.load(Ordering::SeqCst) {
+            ServingStatus::Serving
+        } else {
+            ServingStatus::NotServing
+        };
+        self.status.send_if_modified(|current| {
+            let changed = *current != status;
+            *current = status;
+            changed
+        });
+    }
+
+    pub fn server(&self) -> HealthServer<HealthState> {
+        HealthServer::new(self.clone())
+    }
+
+    fn service_status(&self, service: &str) -> Result<ServingStatus, Status> {
+        if !service.is_empty() {
+            return Err(Status::not_found(format!("unknown service {service:?}")));
+        }
+        Ok(*self.status.borrow())
+    }
+
+    /// What `Check` currently answers for the whole server.
+    #[cfg(test)]
+    pub async fn reported(&self) -> ServingStatus {
+        self.check(Request::new(HealthCheckRequest::default()))
+            .await
+            .unwrap()
+            .into_inner()
+            .status()
+    }
+}
+
+#[tonic::async_trait]
+impl Health for HealthState {
+    async fn check(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        let status = self.service_status(&request.get_ref().service)?;
+        Ok(Response::new(HealthCheckResponse {
+            status: status.into(),
+        }))
+    }
+
+    type WatchStream = Pin<Box<dyn Stream<Item = Result<HealthCheckResponse, Status>> + Send>>;
+
+    async fn watch(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        self.service_status(&request.get_ref().service)?;
+        let updates = WatchStream::new(self.status.subscribe()).map(|status| {
+            Ok(HealthCheckResponse {
+                status: status.into(),
+            })
+        });
+        Ok(Response::new(Box::pin(updates)))
+    }
+}
+
+#[derive(Debug)]
+pub struct HealthMonitor {
+    /// Consecutive failed probes since the last success.
+    failures: u32,
+    /// Number of consecutive failures after which the service reports `NotServing`.
+    threshold: u32,
+}
+
+impl HealthMonitor {
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            failures: 0,
+            threshold: threshold.max(1),
+        }
+    }
+
+    /// Records a probe result and returns whether the service should report serving.
+    pub fn observe(&mut self, healthy: bool) -> bool {
+        if healthy {
+            self.failures = 0;
+        } else {
+            self.failures = self.failures.saturating_add(1);
+        }
+        self.failures < self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flips_only_after_threshold_consecutive_failures() {
+        let mut monitor = HealthMonitor::new(3);
+
+        assert!(monitor.observe(false));
+        assert!(monitor.observe(true));
+        assert!(monitor.observe(false));
+        assert!(monitor.observe(false));
+        assert!(!monitor.observe(false));
+        assert!(monitor.observe(true));
+    }
+}
@@ -1,20 +1,86 @@
-use std::sync::{Arc, Mutex};
+// `tonic::Status` is large, but it is the error type of every handler and helper here.
+#![allow(clippy::result_large_err)]
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    future::Future,
+    net::IpAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, TryLockError,
+    },
+};
 
 use anyhow::{anyhow, Result};
 use hyper::{
     header::{HeaderName, HeaderValue},
+    server::conn::AddrIncoming,
+    service::make_service_fn,
     HeaderMap,
 };
 use once_cell::sync::Lazy;
 use opentelemetry::{global, trace::TraceError, trace::TracerProvider, KeyValue};
 use opentelemetry_otlp::WithExportConfig;
-use opentelemetry_sdk::{propagation::TraceContextPropagator, runtime, Resource};
+use opentelemetry_sdk::{runtime, trace::Sampler, Resource};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use shuttle_runtime::Service;
-use tonic::{metadata::MetadataMap, transport::Server as TonicServer, Response, Status};
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    Stream, StreamExt,
+};
+use tonic::{
+    metadata::MetadataMap, server::NamedService, transport::Server as TonicServer, Response,
+    Status, Streaming,
+};
 use tonic_tracing_opentelemetry::middleware::server;
-use tower::make::Shared;
+use tower::ServiceExt as _;
 use tracing_subscriber::layer::SubscriberExt;
 
+mod audit;
+mod changes;
+mod circuit_breaker;
+mod config;
+mod connections;
+mod health;
+mod metrics;
+mod middleware;
+mod pagination;
+mod propagation;
+mod rate_limit;
+mod read_cache;
+mod redact;
+mod schema_version;
+mod selftest;
+mod slug;
+mod snapshot;
+mod synthetic;
+mod telemetry;
+mod undo;
+mod validation;
+mod watchers;
+
+use changes::ChangeFeed;
+use circuit_breaker::CircuitBreaker;
+use config::{Config, StorageBackend};
+use connections::{LimitedConn, LimitedIncoming};
+use health::{HealthMonitor, HealthState};
+use metrics::{Metrics, READ_CACHE_METRIC, STORE_LOCK_WAIT_METRIC};
+use middleware::{
+    AccessLog, AccessLogLayer, AuditLayer, AuthLayer, CatchPanicLayer, ChaosLayer,
+    CircuitBreakerLayer, ClientId, CompressionLayer, ConcurrencyLayer, ConnectionInfo,
+    ErrorMetricsLayer, GrpcWebContentTypeLayer, InFlightLayer, MetadataLimitLayer,
+    MethodTimeoutLayer, MinClientVersionLayer, ReadOnlyLayer, RequestIdLayer, RequestSizeLayer,
+    Role, TlsOnlyLayer, TraceTrustLayer,
+};
+use pagination::{paginate, paginate_scoped, Page};
+use rate_limit::RateLimiter;
+use read_cache::ReadCache;
+use telemetry::{ErrorAwareSampler, TracesExporter};
+use undo::{Before, UndoLog};
+use validation::{news_violations, normalize_email, validate_news, validate_user, Violations};
+use watchers::WatcherLimit;
+
 pub mod grpc {
     pub mod news {
         tonic::include_proto!("news");
@@ -22,42 +88,117 @@ pub mod grpc {
     pub mod posts {
         tonic::include_proto!("posts");
     }
+    #[allow(clippy::large_enum_variant)]
     pub mod users {
         tonic::include_proto!("users");
     }
+    pub mod diagnostics {
+        tonic::include_proto!("diagnostics");
+    }
+    pub mod snapshot {
+        tonic::include_proto!("snapshot");
+    }
+    pub mod events {
+        tonic::include_proto!("events");
+    }
+    pub mod meta {
+        tonic::include_proto!("meta");
+    }
     pub(crate) const FILE_DESCRIPTOR_SET: &[u8] =
         tonic::include_file_descriptor_set!("grpc_descriptor");
 }
 
+use grpc::diagnostics::diagnostics_service_server::{DiagnosticsService, DiagnosticsServiceServer};
+use grpc::diagnostics::{
+    AuditEntry, AuditLog as AuditLogResponse, DuplicatePostTitles, LastModified, MethodList,
+    OrphanedPosts, PingRequest, PingResponse, PostTitleGroup, ServerStatus, ServiceMethods,
+    UndoResponse, UndoneChange,
+};
+use grpc::events::{ChangeEvent, ChangeType, EntityType};
+use grpc::meta::ResponseMeta;
 use grpc::news::news_service_server::{NewsService, NewsServiceServer};
-use grpc::news::{MultipleNewsId, News, NewsId, NewsList};
+use grpc::news::{
+    news_lookup, ConvertNewsToPostRequest, DayCount, DiffNewsRequest, FeedRequest, FieldChange,
+    MultipleNewsId, News, NewsByAuthorRequest, NewsDiff, NewsEvent, NewsHistogram, NewsId,
+    NewsList, NewsLookup, NewsPage, NewsSearchQuery, NewsSlug, NewsStatistics, NewsTitle,
+    NewsTitles, NewsWithAuthor, OlderThanRequest, ReindexReport, RelatedNewsRequest, SampleRequest,
+    Status as NewsStatus, SyncDelta, SyncEntry, ValidationResult, Violation, WatchNewsRequest,
+};
 use grpc::posts::post_service_server::{PostService, PostServiceServer};
 use grpc::posts::{
-    DeleteResponse as PostDeleteResponse, Filter as PostFilter, Post, PostList, PostRequest,
-    PostResponse,
+    move_post_result, DeletePostsByUserRequest, DeletePostsByUserResponse,
+    DeleteResponse as PostDeleteResponse, Filter as PostFilter, ListPostsSinceRequest,
+    ListPostsSinceResponse, MovePostResult, MoveUserPostsRequest, MoveUserPostsResponse, Post,
+    PostBodyChunk, PostCursor, PostIds, PostList, PostPrefixQuery, PostRequest, PostResponse,
+    PostRevision, PostRevisions, PostWordCount, PostsByIds, ScopedPostRequest, TransferPostRequest,
 };
+use grpc::snapshot::Snapshot;
 use grpc::users::user_service_server::{UserService, UserServiceServer};
 use grpc::users::{
-    DeleteResponse as UserDeleteResponse, Filter as UserFilter, PatchUserRequest, User, UserList,
-    UserRequest, UserResponse,
+    patch_user_result, BatchPatchUsersRequest, BatchPatchUsersResponse, Company,
+    CreatedBetweenRequest, DeleteResponse as UserDeleteResponse, EmailDomainCounts,
+    Filter as UserFilter, LoginHint, PatchUserError, PatchUserRequest, PatchUserResult,
+    PostWithAuthor, PostsWithAuthors, User, UserActivity, UserActivityRequest, UserList,
+    UserPostsRequest, UserProfile, UserRequest, UserResponse, UserWithPosts,
 };
 
-#[derive(Debug, Default, Clone)]
+/// Versions of key dependencies, captured from `Cargo.toml` by the build script.
+const TONIC_VERSION: &str = env!("TONIC_VERSION");
+const OPENTELEMETRY_VERSION: &str = env!("OPENTELEMETRY_VERSION");
+
+/// Window over which `LOGIN_HINT_RATE_LIMIT` is counted.
+const LOGIN_HINT_WINDOW: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Interval between sweeps for soft-deleted entities past their retention.
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Creation time given to the seeded records: 2024-01-01T00:00:00Z.
+const SEED_TIMESTAMP: i64 = 1_704_067_200_000;
+
+#[derive(Debug, Clone)]
 pub struct MyGrpcService {
     news: Arc<Mutex<Vec<News>>>, // Using a simple vector to store news items in memory
     posts: Arc<Mutex<Vec<Post>>>,
     users: Arc<Mutex<Vec<User>>>,
+    /// Prior versions of each news item, newest first, bounded by `HISTORY_DEPTH`.
+    news_history: Arc<Mutex<HashMap<i32, VecDeque<News>>>>,
+    /// Who changed what in each update of a post, newest first, bounded by `HISTORY_DEPTH`.
+    post_revisions: Arc<Mutex<HashMap<i32, VecDeque<PostRevision>>>>,
+    news_events: ChangeFeed<NewsEvent>,
+    /// Every mutation across all stores, retaining the last `CHANGE_LOG_CAPACITY` events.
+    changes: ChangeFeed<ChangeEvent>,
+    /// Who made each mutation, appended from `publish_change`.
+    audit: audit::AuditLog,
+    /// Before-states of the mutations retained in `changes`, for `UndoLastChange`.
+    undo: UndoLog,
+    /// Responses of cached reads, cleared from `publish_change`.
+    read_cache: ReadCache,
+    /// Open watch streams, bounded by `MAX_WATCHERS`.
+    watchers: WatcherLimit,
+    /// `GetUserLoginHint` calls per peer address, bounded by `LOGIN_HINT_RATE_LIMIT`.
+    login_hints: Arc<RateLimiter<Option<IpAddr>>>,
+    config: Arc<Config>,
+    metrics: Arc<Metrics>,
+    /// Startup readiness and store health, served as `grpc.health.v1.Health`.
+    health: HealthState,
+    tracing_enabled: bool,
+    /// Source of randomness for sampling; seeded in tests for determinism.
+    rng: Arc<Mutex<StdRng>>,
 }
 
 impl MyGrpcService {
     fn new() -> MyGrpcService {
-        let news = vec![
+        let mut news = vec![
             News {
                 id: 1,
                 title: "Note 1".into(),
                 body: "Content 1".into(),
                 post_image: "Post image 1".into(),
                 status: 0,
+                created_at: SEED_TIMESTAMP,
+                updated_at: SEED_TIMESTAMP,
+                author_id: Some(1),
+                ..Default::default()
             },
             News {
                 id: 2,
@@ -65,6 +206,10 @@ impl MyGrpcService {
                 body: "Content 2".into(),
                 post_image: "Post image 2".into(),
                 status: 1,
+                created_at: SEED_TIMESTAMP,
+                updated_at: SEED_TIMESTAMP,
+                author_id: Some(1),
+                ..Default::default()
             },
             News {
                 id: 3,
@@ -72,6 +217,9 @@ impl MyGrpcService {
                 body: "Content 3".into(),
                 post_image: "Post image 3".into(),
                 status: 1,
+                created_at: SEED_TIMESTAMP,
+                updated_at: SEED_TIMESTAMP,
+                ..Default::default()
             },
             News {
                 id: 4,
@@ -79,6 +227,9 @@ impl MyGrpcService {
                 body: "Content 4".into(),
                 post_image: "Post image 4".into(),
                 status: 1,
+                created_at: SEED_TIMESTAMP,
+                updated_at: SEED_TIMESTAMP,
+                ..Default::default()
             },
             News {
                 id: 5,
@@ -86,37 +237,440 @@ impl MyGrpcService {
                 body: "Content 5".into(),
                 post_image: "Post image 5".into(),
                 status: 1,
+                created_at: SEED_TIMESTAMP,
+                updated_at: SEED_TIMESTAMP,
+                ..Default::default()
             },
         ];
+        for item in &mut news {
+            item.slug = slug::slugify(&item.title);
+        }
         let posts = vec![
             Post {
                 user_id: 1,
                 id: 1,
                 title: "Post 1".into(),
                 body: "Body 1".into(),
+                created_at: SEED_TIMESTAMP,
+                updated_at: SEED_TIMESTAMP,
+                ..Default::default()
             },
             Post {
                 user_id: 1,
                 id: 2,
                 title: "Post 2".into(),
                 body: "Body 2".into(),
+                created_at: SEED_TIMESTAMP + 1000,
+                updated_at: SEED_TIMESTAMP + 1000,
+                ..Default::default()
             },
         ];
         let users = vec![User {
             id: 1,
             name: "Leanne Graham".into(),
             username: "Bret".into(),
-            email: "Sincere@april.biz".into(),
+            email: "sincere@april.biz".into(),
             address: None,
             phone: "1-770-736-8031 x56442".into(),
             website: "hildegard.org".into(),
             company: None,
+            created_at: SEED_TIMESTAMP,
+            updated_at: SEED_TIMESTAMP,
+            ..Default::default()
         }];
         MyGrpcService {
             news: Arc::new(Mutex::new(news)),
             posts: Arc::new(Mutex::new(posts)),
             users: Arc::new(Mutex::new(users)),
+            news_history: Arc::default(),
+            post_revisions: Arc::default(),
+            news_events: ChangeFeed::new(),
+            changes: ChangeFeed::with_retention(Config::default().change_log_capacity),
+            audit: audit::AuditLog::default(),
+            undo: UndoLog::with_capacity(Config::default().change_log_capacity),
+            read_cache: ReadCache::new(Config::default().read_cache_ttl),
+            watchers: WatcherLimit::new(Config::default().max_watchers),
+            login_hints: Arc::new(RateLimiter::new(
+                Config::default().login_hint_rate_limit,
+                LOGIN_HINT_WINDOW,
+            )),
+            config: Arc::new(Config::default()),
+            metrics: Arc::default(),
+            health: HealthState::default(),
+            tracing_enabled: false,
+            rng: Arc::new(Mutex::new(StdRng::from_entropy())),
+        }
+    }
+
+    /// Builds the service with the stores selected by `config.storage_backend`.
+    fn from_config(config: Config) -> MyGrpcService {
+        let service = match config.storage_backend {
+            StorageBackend::Memory => MyGrpcService::new(),
+        };
+        if let Some(count) = config.seed_count {
+            let dataset = synthetic::generate(count);
+            *service.lock_news() = dataset.news;
+            *service.lock_posts() = dataset.posts;
+            *service.lock_users() = dataset.users;
+        }
+        MyGrpcService {
+            changes: ChangeFeed::with_retention(config.change_log_capacity),
+            undo: UndoLog::with_capacity(config.change_log_capacity),
+            read_cache: ReadCache::new(config.read_cache_ttl),
+            watchers: WatcherLimit::new(config.max_watchers),
+            login_hints: Arc::new(RateLimiter::new(
+                config.login_hint_rate_limit,
+                LOGIN_HINT_WINDOW,
+            )),
+            config: Arc::new(config),
+            ..service
+        }
+    }
+
+    /// Serves `method` from the read cache while fresh, otherwise calls `load`, which must read
+    /// only entities of `entity_type`, and caches its result.
+    fn cached_read<Req, Resp>(
+        &self,
+        method: &'static str,
+        entity_type: EntityType,
+        request: &Req,
+        load: impl FnOnce() -> Resp,
+    ) -> Resp
+    where
+        Req: prost::Message,
+        Resp: prost::Message + Default,
+    {
+        if !self.read_cache.is_enabled() {
+            return load();
+        }
+        let key = request.encode_to_vec();
+        if let Some(cached) = self.read_cache.get(method, &key) {
+            if let Ok(response) = Resp::decode(cached.as_slice()) {
+                self.metrics.record(
+                    READ_CACHE_METRIC,
+                    &[("method", method), ("result", "hit")],
+                    1.0,
+                );
+                return response;
+            }
+        }
+        let generation = self.read_cache.generation(entity_type);
+        let response = load();
+        self.read_cache.insert(
+            method,
+            key,
+            entity_type,
+            generation,
+            response.encode_to_vec(),
+        );
+        self.metrics.record(
+            READ_CACHE_METRIC,
+            &[("method", method), ("result", "miss")],
+            1.0,
+        );
+        response
+    }
+
+    /// Copies every store. Lock order: news, then users, then posts.
+    fn snapshot(&self) -> Snapshot {
+        let news = self.lock_news();
+        let users = self.lock_users();
+        let posts = self.lock_posts();
+        Snapshot {
+            news: news.clone(),
+            posts: posts.clone(),
+            users: users.clone(),
+        }
+    }
+
+    /// Replaces the stores with the snapshot at `SNAPSHOT_PATH`, if set and present.
+    fn restore_snapshot(&self) -> Result<()> {
+        let Some(path) = &self.config.snapshot_path else {
+            return Ok(());
+        };
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(anyhow!("failed to read snapshot {}: {e}", path.display())),
+        };
+        let snapshot = snapshot::decode(&bytes, self.config.snapshot_format)?;
+        *self.lock_news() = snapshot.news;
+        *self.lock_users() = snapshot.users;
+        *self.lock_posts() = snapshot.posts;
+        Ok(())
+    }
+
+    /// Writes every store to `SNAPSHOT_PATH`, if set, replacing the previous file only once
+    /// the new one is complete.
+    fn save_snapshot(&self) -> Result<()> {
+        let Some(path) = &self.config.snapshot_path else {
+            return Ok(());
+        };
+        let bytes = snapshot::encode(&self.snapshot(), self.config.snapshot_format)?;
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, bytes)
+            .and_then(|()| std::fs::rename(&partial, path))
+            .map_err(|e| anyhow!("failed to write snapshot {}: {e}", path.display()))
+    }
+
+    fn lock_news(&self) -> MutexGuard<'_, Vec<News>> {
+        self.lock_store("news", &self.news)
+    }
+
+    fn lock_posts(&self) -> MutexGuard<'_, Vec<Post>> {
+        self.lock_store("posts", &self.posts)
+    }
+
+    fn lock_users(&self) -> MutexGuard<'_, Vec<User>> {
+        self.lock_store("users", &self.users)
+    }
+
+    /// Locks a store, recording how long the acquisition waited into `store_lock_wait_ms`
+    /// whenever the lock was already held.
+    fn lock_store<'a, T>(&self, store: &str, mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+        if !self.config.store_lock_metrics {
+            return mutex.lock().unwrap();
+        }
+        match mutex.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::WouldBlock) => {
+                let started_at = std::time::Instant::now();
+                let guard = mutex.lock().unwrap();
+                self.metrics.record(
+                    STORE_LOCK_WAIT_METRIC,
+                    &[("store", store)],
+                    started_at.elapsed().as_secs_f64() * 1000.0,
+                );
+                guard
+            }
+            Err(TryLockError::Poisoned(e)) => panic!("store lock poisoned: {e}"),
+        }
+    }
+
+    /// A store is unhealthy once a writer panicked while holding its lock, since every later
+    /// access to it will fail.
+    fn stores_healthy(&self) -> bool {
+        !self.news.is_poisoned() && !self.posts.is_poisoned() && !self.users.is_poisoned()
+    }
+
+    /// Probes the stores every `HEALTH_CHECK_INTERVAL_MS`, updating the health status.
+    async fn monitor_health(self) {
+        let mut monitor = HealthMonitor::new(self.config.health_failure_threshold);
+        let mut interval = tokio::time::interval(self.config.health_check_interval);
+        loop {
+            interval.tick().await;
+            let healthy = self.stores_healthy();
+            let serving = monitor.observe(healthy);
+            if self.health.set_healthy(serving) {
+                tracing::warn!(serving, "store health changed");
+            }
+        }
+    }
+
+    /// Purges `DELETED` news once `SOFT_DELETE_RETENTION_DAYS` have passed.
+    async fn purge_soft_deleted_periodically(self) {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let purged = self.purge_soft_deleted(now_millis());
+            if !purged.is_empty() {
+                tracing::info!(?purged, "purged soft-deleted news");
+            }
+        }
+    }
+
+    /// Removes news that has been `DELETED` for longer than its retention as of `now`,
+    /// publishing a `DELETED` event for each, and returns the purged ids. Deletion time is
+    /// the item's `updated_at`.
+    fn purge_soft_deleted(&self, now: i64) -> Vec<i32> {
+        let Some(retention) = self.config.soft_delete_retention else {
+            return Vec::new();
+        };
+        let cutoff = now - retention.as_millis() as i64;
+        let mut lock = self.lock_news();
+        let (purged, kept) = std::mem::take(&mut *lock)
+            .into_iter()
+            .partition::<Vec<_>, _>(|n| n.status() == NewsStatus::Deleted && n.updated_at < cutoff);
+        *lock = kept;
+        let mut history = self.news_history.lock().unwrap();
+        purged
+            .into_iter()
+            .map(|news| {
+                history.remove(&news.id);
+                let id = news.id;
+                self.notify_news(ChangeType::Deleted, news);
+                id
+            })
+            .collect()
+    }
+
+    /// Rejects a `body` longer than `limit` bytes.
+    fn check_body_len(&self, body: &str, limit: Option<usize>) -> std::result::Result<(), Status> {
+        match limit {
+            Some(limit) if body.len() > limit => Err(Status::invalid_argument(format!(
+                "body is {} bytes; at most {limit} are allowed",
+                body.len()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Every check AddNews rejects `news` for once schema defaults are applied, which is also
+    /// exactly what ValidateNews reports.
+    fn create_news_violations(&self, news: &News) -> Violations {
+        news_violations(news, self.config.create_body_limit(), true)
+    }
+
+    /// Rejects an insert into a store currently holding `len` entities if it would exceed
+    /// `MAX_ENTITIES`.
+    fn check_capacity(&self, len: usize) -> std::result::Result<(), Status> {
+        match self.config.max_entities {
+            Some(max) if len >= max => Err(Status::resource_exhausted("store is full")),
+            _ => Ok(()),
+        }
+    }
+
+    /// Starts timing a list request if the client sent `x-include-meta: true`.
+    fn start_meta<T>(&self, request: &tonic::Request<T>) -> Option<MetaTimer> {
+        let metadata = request.metadata();
+        let requested = metadata
+            .get(INCLUDE_META_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"));
+        if !requested {
+            return None;
+        }
+        let request_id = metadata
+            .get(self.config.request_id_header.as_str())
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        Some(MetaTimer {
+            request_id,
+            started_at: std::time::Instant::now(),
+        })
+    }
+
+    /// In `STRICT_MODE`, rejects a create request whose client set the server-assigned id.
+    fn check_client_id(&self, id: i32) -> std::result::Result<(), Status> {
+        if self.config.strict_mode && id != 0 {
+            return Err(Status::invalid_argument(
+                "id is assigned by the server and must not be set on create",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Logs a user mutation, masking contact details when `LOG_REDACT` is enabled.
+    fn log_user(&self, action: &str, user: &User) {
+        let (email, phone) = if self.config.log_redact {
+            (redact::email(&user.email), redact::phone(&user.phone))
+        } else {
+            (user.email.clone(), user.phone.clone())
+        };
+        tracing::info!(id = user.id, %email, %phone, "{action} user");
+    }
+
+    /// Stores `previous` as the newest prior version of its news item.
+    ///
+    /// Called while holding the news lock, before the item is modified.
+    fn record_news_version(&self, previous: News) {
+        let depth = self.config.history_depth;
+        let mut history = self.news_history.lock().unwrap();
+        let versions = history.entry(previous.id).or_default();
+        versions.push_front(previous);
+        versions.truncate(depth);
+    }
+
+    /// Before-state of news `id` for the undo log, with its version history. Must be taken
+    /// before the mutation touches either.
+    fn news_before(&self, id: i32, item: Option<News>) -> Before {
+        let history = self.news_history.lock().unwrap().get(&id).cloned();
+        Before::News { id, item, history }
+    }
+
+    /// Before-state of post `id` for the undo log, with its revisions. Must be taken before
+    /// the mutation touches either.
+    fn post_before(&self, id: i32, item: Option<Post>) -> Before {
+        let revisions = self.post_revisions.lock().unwrap().get(&id).cloned();
+        Before::Post {
+            id,
+            item,
+            revisions,
+        }
+    }
+
+    /// Records the update of `before` into `after` by `client_id` as the post's newest revision.
+    ///
+    /// Called while holding the posts lock.
+    fn record_post_revision(&self, before: &Post, after: &Post, client_id: Option<&ClientId>) {
+        let changed_fields = [
+            ("user_id", before.user_id != after.user_id),
+            ("title", before.title != after.title),
+            ("body", before.body != after.body),
+        ]
+        .into_iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(field, _)| field.to_string())
+        .collect();
+        let mut revisions = self.post_revisions.lock().unwrap();
+        let revisions = revisions.entry(after.id).or_default();
+        revisions.push_front(PostRevision {
+            version: revisions.front().map_or(1, |latest| latest.version + 1),
+            client_id: client_id.map(|ClientId(id)| id.clone()).unwrap_or_default(),
+            changed_fields,
+            updated_at: after.updated_at,
+        });
+        revisions.truncate(self.config.history_depth);
+    }
+
+    /// Publishes a change to the all-entities feed, returning its sequence.
+    ///
+    /// Must be called while holding the lock of the mutated store so events follow mutation
+    /// order.
+    fn publish_change(
+        &self,
+        entity_type: EntityType,
+        id: i32,
+        user_id: Option<i32>,
+        change_type: ChangeType,
+    ) -> u64 {
+        self.read_cache.invalidate(entity_type);
+        let sequence = self.changes.publish(|sequence| ChangeEvent {
+            sequence,
+            entity_type: entity_type.into(),
+            id,
+            change_type: change_type.into(),
+            lagged: 0,
+            user_id,
+        });
+        if let Some(context) = middleware::audit::current() {
+            self.audit.append(AuditEntry {
+                sequence: 0,
+                client_id: context.client_id,
+                method: context.method,
+                entity_type: entity_type.into(),
+                entity_id: id,
+                timestamp: now_millis(),
+            });
         }
+        sequence
+    }
+
+    /// Publishes a news change to `WatchNews` subscribers and the all-entities feed, returning
+    /// its all-entities sequence.
+    ///
+    /// Must be called while holding the news lock.
+    fn notify_news(&self, change_type: ChangeType, news: News) -> u64 {
+        let sequence = self.publish_change(EntityType::News, news.id, news.author_id, change_type);
+        self.news_events.publish(|sequence| NewsEvent {
+            sequence,
+            change_type: change_type.into(),
+            news: Some(news),
+            lagged: 0,
+            batched: Vec::new(),
+        });
+        sequence
     }
 }
 
@@ -124,10 +678,43 @@ impl MyGrpcService {
 impl NewsService for MyGrpcService {
     async fn get_all_news(
         &self,
-        _request: tonic::Request<()>,
+        request: tonic::Request<()>,
     ) -> std::result::Result<Response<NewsList>, Status> {
-        let lock = self.news.lock().unwrap();
-        let reply = NewsList { news: lock.clone() };
+        let meta = self.start_meta(&request);
+        // Cached without `meta`, which belongs to this request alone.
+        let mut reply = self.cached_read(
+            "/news.NewsService/GetAllNews",
+            EntityType::News,
+            &(),
+            || NewsList {
+                news: self.lock_news().clone(),
+                meta: None,
+            },
+        );
+        reply.meta = meta.map(MetaTimer::finish);
+        Ok(Response::new(reply))
+    }
+
+    async fn list_news_titles(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<NewsTitles>, Status> {
+        let reply = self.cached_read(
+            "/news.NewsService/ListNewsTitles",
+            EntityType::News,
+            &(),
+            || NewsTitles {
+                titles: self
+                    .lock_news()
+                    .iter()
+                    .map(|n| NewsTitle {
+                        id: n.id,
+                        title: n.title.clone(),
+                        status: n.status,
+                    })
+                    .collect(),
+            },
+        );
         Ok(Response::new(reply))
     }
 
@@ -136,7 +723,7 @@ impl NewsService for MyGrpcService {
         request: tonic::Request<NewsId>,
     ) -> std::result::Result<Response<News>, Status> {
         let id = request.into_inner().id;
-        let lock = self.news.lock().unwrap();
+        let lock = self.lock_news();
         let item = lock.iter().find(|&n| n.id == id).cloned();
         match item {
             Some(news) => Ok(Response::new(news)),
@@ -148,19 +735,23 @@ impl NewsService for MyGrpcService {
         &self,
         request: tonic::Request<MultipleNewsId>,
     ) -> std::result::Result<Response<NewsList>, Status> {
+        let meta = self.start_meta(&request);
         let ids = request
             .into_inner()
             .ids
             .into_iter()
             .map(|id| id.id)
             .collect::<Vec<_>>();
-        let lock = self.news.lock().unwrap();
+        let lock = self.lock_news();
         let news_items: Vec<News> = lock
             .iter()
             .filter(|n| ids.contains(&n.id))
             .cloned()
             .collect();
-        Ok(Response::new(NewsList { news: news_items }))
+        Ok(Response::new(NewsList {
+            news: news_items,
+            meta: meta.map(MetaTimer::finish),
+        }))
     }
 
     async fn delete_news(
@@ -168,298 +759,4755 @@ impl NewsService for MyGrpcService {
         request: tonic::Request<NewsId>,
     ) -> std::result::Result<Response<()>, Status> {
         let id = request.into_inner().id;
-        let mut lock = self.news.lock().unwrap();
-        let len_before = lock.len();
-        lock.retain(|news| news.id != id);
-        let len_after = lock.len();
+        let mut lock = self.lock_news();
+        match lock.iter().position(|news| news.id == id) {
+            Some(index) => {
+                let news = lock.remove(index);
+                let before = self.news_before(id, Some(news.clone()));
+                self.news_history.lock().unwrap().remove(&id);
+                let sequence = self.notify_news(ChangeType::Deleted, news);
+                self.undo.record(sequence, vec![before]);
+                Ok(Response::new(()))
+            }
+            None => Err(Status::not_found("News not found")),
+        }
+    }
+
+    async fn delete_news_if_unpublished(
+        &self,
+        request: tonic::Request<NewsId>,
+    ) -> std::result::Result<Response<()>, Status> {
+        let id = request.into_inner().id;
+        let mut lock = self.lock_news();
+        let index = lock
+            .iter()
+            .position(|news| news.id == id)
+            .ok_or_else(|| Status::not_found("News not found"))?;
+        if lock[index].status() == NewsStatus::Published {
+            return Err(Status::failed_precondition(
+                "published news cannot be deleted; unpublish it first",
+            ));
+        }
+        let news = lock.remove(index);
+        let before = self.news_before(id, Some(news.clone()));
+        self.news_history.lock().unwrap().remove(&id);
+        let sequence = self.notify_news(ChangeType::Deleted, news);
+        self.undo.record(sequence, vec![before]);
+        Ok(Response::new(()))
+    }
 
-        if len_before == len_after {
-            Err(Status::not_found("News not found"))
-        } else {
-            let x = Response::new(());
-            Ok(x)
+    async fn publish_news(
+        &self,
+        request: tonic::Request<NewsId>,
+    ) -> std::result::Result<Response<News>, Status> {
+        let id = request.into_inner().id;
+        let mut lock = self.lock_news();
+        let news = lock
+            .iter_mut()
+            .find(|n| n.id == id)
+            .ok_or_else(|| Status::not_found("News not found"))?;
+        if news.status() != NewsStatus::Draft {
+            return Err(Status::failed_precondition(format!(
+                "only drafts can be published; news is {}",
+                news.status().as_str_name()
+            )));
         }
+        let before = self.news_before(id, Some(news.clone()));
+        self.record_news_version(news.clone());
+        news.set_status(NewsStatus::Published);
+        news.published_at = now_millis();
+        news.updated_at = news.published_at;
+        let news = news.clone();
+        let sequence = self.notify_news(ChangeType::Updated, news.clone());
+        self.undo.record(sequence, vec![before]);
+        Ok(Response::new(news))
     }
 
     async fn edit_news(
         &self,
         request: tonic::Request<News>,
     ) -> std::result::Result<Response<News>, Status> {
-        let new_news = request.into_inner();
-        let mut lock = self.news.lock().unwrap();
+        let mut new_news = request.into_inner();
+        let validate_only = std::mem::take(&mut new_news.validate_only);
+        validate_news(&new_news, self.config.update_body_limit(), false)?;
+        let mut lock = self.lock_news();
+        let slug = slug::unique(slug::slugify(&new_news.title), |candidate| {
+            lock.iter()
+                .any(|n| n.id != new_news.id && n.slug == candidate)
+        });
+        new_news.slug = slug;
         if let Some(news) = lock.iter_mut().find(|n| n.id == new_news.id) {
+            if validate_only {
+                return Ok(Response::new(new_news));
+            }
+            let before = self.news_before(news.id, Some(news.clone()));
+            self.record_news_version(news.clone());
             news.title = new_news.title.clone();
             news.body = new_news.body.clone();
             news.post_image = new_news.post_image.clone();
+            news.author_id = new_news.author_id;
+            news.slug = new_news.slug.clone();
+            news.updated_at = now_millis();
+            let news = news.clone();
+            let sequence = self.notify_news(ChangeType::Updated, news);
+            self.undo.record(sequence, vec![before]);
             return Ok(Response::new(new_news));
         }
         Err(Status::not_found("News not found"))
     }
 
+    async fn diff_news(
+        &self,
+        request: tonic::Request<DiffNewsRequest>,
+    ) -> std::result::Result<Response<NewsDiff>, Status> {
+        let request = request.into_inner();
+        let candidate = request.candidate.unwrap_or_default();
+        let lock = self.lock_news();
+        let current = lock
+            .iter()
+            .find(|n| n.id == request.id)
+            .ok_or_else(|| Status::not_found("News not found"))?;
+        let author = |news: &News| news.author_id.map(|id| id.to_string()).unwrap_or_default();
+        // The fields EditNews copies from its request.
+        let fields = [
+            ("title", current.title.clone(), candidate.title.clone()),
+            ("body", current.body.clone(), candidate.body.clone()),
+            (
+                "postImage",
+                current.post_image.clone(),
+                candidate.post_image.clone(),
+            ),
+            ("author_id", author(current), author(&candidate)),
+        ];
+        let changes = fields
+            .into_iter()
+            .filter(|(_, old_value, new_value)| old_value != new_value)
+            .map(|(field, old_value, new_value)| FieldChange {
+                field: field.into(),
+                old_value,
+                new_value,
+            })
+            .collect();
+        Ok(Response::new(NewsDiff { changes }))
+    }
+
+    async fn validate_news(
+        &self,
+        request: tonic::Request<News>,
+    ) -> std::result::Result<Response<ValidationResult>, Status> {
+        let version =
+            schema_version::negotiate(request.metadata(), self.config.default_schema_version)?;
+        let mut news = request.into_inner();
+        schema_version::default_news_fields(&mut news, version);
+        let violations = self
+            .create_news_violations(&news)
+            .iter()
+            .map(|(field, description)| Violation {
+                field: field.into(),
+                description: description.into(),
+            })
+            .collect();
+        Ok(Response::new(ValidationResult { violations }))
+    }
+
     async fn add_news(
         &self,
         request: tonic::Request<News>,
     ) -> std::result::Result<Response<News>, Status> {
+        let version =
+            schema_version::negotiate(request.metadata(), self.config.default_schema_version)?;
         let mut news = request.into_inner();
-        let mut lock = self.news.lock().unwrap();
+        let validate_only = std::mem::take(&mut news.validate_only);
+        self.check_client_id(news.id)?;
+        schema_version::default_news_fields(&mut news, version);
+        self.create_news_violations(&news).into_result()?;
+        let mut lock = self.lock_news();
+        self.check_capacity(lock.len())?;
         let new_id = lock.iter().map(|n| n.id).max().unwrap_or(0) + 1; // Simple ID generation
         news.id = new_id;
+        news.slug = slug::unique(slug::slugify(&news.title), |candidate| {
+            lock.iter().any(|n| n.slug == candidate)
+        });
+        news.created_at = now_millis();
+        news.updated_at = news.created_at;
+        if validate_only {
+            return Ok(Response::new(news));
+        }
         lock.push(news.clone());
+        let before = self.news_before(news.id, None);
+        let sequence = self.notify_news(ChangeType::Created, news.clone());
+        self.undo.record(sequence, vec![before]);
         Ok(Response::new(news))
     }
-}
 
-#[tonic::async_trait]
-impl PostService for MyGrpcService {
-    async fn list_posts(
+    async fn get_news_with_author(
         &self,
-        request: tonic::Request<PostFilter>,
-    ) -> std::result::Result<Response<PostList>, Status> {
-        let filter = request.into_inner();
-        let lock = self.posts.lock().unwrap();
-        let posts = match filter.user_id {
-            Some(user_id) => lock
-                .iter()
-                .filter(|p| p.user_id == user_id)
-                .cloned()
-                .collect(),
-            None => lock.clone(),
-        };
-        Ok(Response::new(PostList { posts }))
+        request: tonic::Request<NewsId>,
+    ) -> std::result::Result<Response<NewsWithAuthor>, Status> {
+        let id = request.into_inner().id;
+        let news = self
+            .lock_news()
+            .iter()
+            .find(|n| n.id == id)
+            .cloned()
+            .ok_or_else(|| Status::not_found("News not found"))?;
+        let author = news.author_id.and_then(|author_id| {
+            let users = self.lock_users();
+            users.iter().find(|u| u.id == author_id).cloned()
+        });
+        Ok(Response::new(NewsWithAuthor {
+            news: Some(news),
+            author,
+        }))
     }
 
-    async fn get_post(
+    async fn get_news_history(
         &self,
-        request: tonic::Request<PostRequest>,
-    ) -> std::result::Result<Response<Post>, Status> {
+        request: tonic::Request<NewsId>,
+    ) -> std::result::Result<Response<NewsList>, Status> {
+        let meta = self.start_meta(&request);
         let id = request.into_inner().id;
-        let lock = self.posts.lock().unwrap();
-        let post = lock.iter().find(|p| p.id == id).cloned();
-        match post {
-            Some(post) => Ok(Response::new(post)),
-            None => Err(Status::not_found("Post not found")),
+        let lock = self.lock_news();
+        if !lock.iter().any(|n| n.id == id) {
+            return Err(Status::not_found("News not found"));
         }
+        let history = self.news_history.lock().unwrap();
+        let news = history
+            .get(&id)
+            .map(|versions| versions.iter().cloned().collect())
+            .unwrap_or_default();
+        Ok(Response::new(NewsList {
+            news,
+            meta: meta.map(MetaTimer::finish),
+        }))
     }
 
-    async fn create_post(
+    async fn convert_news_to_post(
         &self,
-        request: tonic::Request<Post>,
-    ) -> std::result::Result<Response<PostResponse>, Status> {
-        let mut post = request.into_inner();
-        let mut lock = self.posts.lock().unwrap();
-        let new_id = lock.iter().map(|p| p.id).max().unwrap_or(0) + 1;
-        post.id = new_id;
-        lock.push(post.clone());
-        Ok(Response::new(PostResponse { post: Some(post) }))
+        request: tonic::Request<ConvertNewsToPostRequest>,
+    ) -> std::result::Result<Response<Post>, Status> {
+        let request = request.into_inner();
+        // Lock order: news, then users, then posts.
+        let mut news = self.lock_news();
+        let index = news
+            .iter()
+            .position(|n| n.id == request.news_id)
+            .ok_or_else(|| Status::not_found("News not found"))?;
+        let users = self.lock_users();
+        if !self.config.skip_author_check && !users.iter().any(|u| u.id == request.user_id) {
+            return Err(Status::failed_precondition("author does not exist"));
+        }
+        let mut posts = self.lock_posts();
+        self.check_capacity(posts.len())?;
+        let now = now_millis();
+        let post = Post {
+            user_id: request.user_id,
+            id: posts.iter().map(|p| p.id).max().unwrap_or(0) + 1,
+            title: news[index].title.clone(),
+            body: news[index].body.clone(),
+            created_at: now,
+            updated_at: now,
+            ..Default::default()
+        };
+        posts.push(post.clone());
+        let mut before = vec![self.post_before(post.id, None)];
+        let mut sequence = self.publish_change(
+            EntityType::Post,
+            post.id,
+            Some(post.user_id),
+            ChangeType::Created,
+        );
+        if request.delete_source {
+            let removed = news.remove(index);
+            before.push(self.news_before(removed.id, Some(removed.clone())));
+            self.news_history.lock().unwrap().remove(&removed.id);
+            sequence = self.notify_news(ChangeType::Deleted, removed);
+        }
+        self.undo.record(sequence, before);
+        Ok(Response::new(post))
     }
 
-    async fn update_post(
+    async fn reindex_news(
         &self,
-        request: tonic::Request<Post>,
-    ) -> std::result::Result<Response<PostResponse>, Status> {
-        let post_update = request.into_inner();
-        let mut lock = self.posts.lock().unwrap();
-        if let Some(post) = lock.iter_mut().find(|p| p.id == post_update.id) {
-            *post = post_update.clone();
-            return Ok(Response::new(PostResponse {
-                post: Some(post_update),
-            }));
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<ReindexReport>, Status> {
+        let mut lock = self.lock_news();
+        let report = reindex_news(&mut lock);
+        for id in &report.reslugged_ids {
+            if let Some(news) = lock.iter().find(|n| n.id == *id) {
+                self.notify_news(ChangeType::Updated, news.clone());
+            }
         }
-        Err(Status::not_found("Post not found"))
+        if !report.anomalies.is_empty() {
+            tracing::warn!(anomalies = ?report.anomalies, "reindexed inconsistent news store");
+        }
+        Ok(Response::new(report))
     }
 
-    async fn delete_post(
+    async fn get_news_by_slug(
         &self,
-        request: tonic::Request<PostRequest>,
-    ) -> std::result::Result<Response<PostDeleteResponse>, Status> {
-        let id = request.into_inner().id;
-        let mut lock = self.posts.lock().unwrap();
-        let len_before = lock.len();
-        lock.retain(|p| p.id != id);
-        if lock.len() < len_before {
-            Ok(Response::new(PostDeleteResponse {
-                success: true,
-                message: "Post deleted".into(),
-            }))
-        } else {
-            Err(Status::not_found("Post not found"))
-        }
+        request: tonic::Request<NewsSlug>,
+    ) -> std::result::Result<Response<News>, Status> {
+        let slug = request.into_inner().slug;
+        self.lock_news()
+            .iter()
+            .find(|n| n.slug == slug)
+            .cloned()
+            .map(Response::new)
+            .ok_or_else(|| Status::not_found("News not found"))
     }
-}
 
-#[tonic::async_trait]
-impl UserService for MyGrpcService {
-    async fn list_users(
+    async fn get_news_by_id_or_slug(
         &self,
-        request: tonic::Request<UserFilter>,
-    ) -> std::result::Result<Response<UserList>, Status> {
-        let filter = request.into_inner();
-        let lock = self.users.lock().unwrap();
-        let users = if filter.id.is_empty() {
-            lock.clone()
-        } else {
-            lock.iter()
-                .filter(|u| filter.id.contains(&u.id))
-                .cloned()
-                .collect()
+        request: tonic::Request<NewsLookup>,
+    ) -> std::result::Result<Response<News>, Status> {
+        let key = request
+            .into_inner()
+            .key
+            .ok_or_else(|| Status::invalid_argument("either id or slug must be set"))?;
+        let lock = self.lock_news();
+        let news = match key {
+            news_lookup::Key::Id(id) => lock.iter().find(|n| n.id == id),
+            news_lookup::Key::Slug(slug) => lock.iter().find(|n| n.slug == slug),
         };
-        Ok(Response::new(UserList { users }))
+        news.cloned()
+            .map(Response::new)
+            .ok_or_else(|| Status::not_found("News not found"))
     }
 
-    async fn get_user(
+    async fn get_news_statistics(
         &self,
-        request: tonic::Request<UserRequest>,
-    ) -> std::result::Result<Response<User>, Status> {
-        let id = request.into_inner().id;
-        let lock = self.users.lock().unwrap();
-        let user = lock.iter().find(|u| u.id == id).cloned();
-        match user {
-            Some(user) => Ok(Response::new(user)),
-            None => Err(Status::not_found("User not found")),
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<NewsStatistics>, Status> {
+        let lock = self.lock_news();
+        let mut stats = NewsStatistics::default();
+        let mut body_chars = 0;
+        for news in lock.iter() {
+            match news.status() {
+                NewsStatus::Published => stats.published += 1,
+                NewsStatus::Draft => stats.draft += 1,
+                NewsStatus::Deleted => stats.deleted += 1,
+            }
+            body_chars += news.body.chars().count();
+            if stats.total == 0 || news.created_at < stats.oldest_created_at {
+                stats.oldest_created_at = news.created_at;
+            }
+            if stats.total == 0 || news.created_at > stats.newest_created_at {
+                stats.newest_created_at = news.created_at;
+            }
+            stats.total += 1;
+        }
+        if stats.total > 0 {
+            stats.average_body_length = body_chars as f64 / stats.total as f64;
         }
+        Ok(Response::new(stats))
     }
 
-    async fn create_user(
+    async fn get_news_created_histogram(
         &self,
-        request: tonic::Request<User>,
-    ) -> std::result::Result<Response<UserResponse>, Status> {
-        let mut user = request.into_inner();
-        let mut lock = self.users.lock().unwrap();
-        let new_id = lock.iter().map(|u| u.id).max().unwrap_or(0) + 1;
-        user.id = new_id;
-        lock.push(user.clone());
-        Ok(Response::new(UserResponse { user: Some(user) }))
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<NewsHistogram>, Status> {
+        let mut days = BTreeMap::new();
+        for news in self.lock_news().iter() {
+            *days
+                .entry(news.created_at.div_euclid(MILLIS_PER_DAY))
+                .or_insert(0) += 1;
+        }
+        let buckets = days
+            .into_iter()
+            .map(|(day, count)| DayCount {
+                date: utc_date(day),
+                count,
+            })
+            .collect();
+        Ok(Response::new(NewsHistogram { buckets }))
     }
 
-    async fn patch_user(
+    async fn get_related_news(
         &self,
-        request: tonic::Request<PatchUserRequest>,
-    ) -> std::result::Result<Response<UserResponse>, Status> {
-        let req = request.into_inner();
-        let mut lock = self.users.lock().unwrap();
-        if let Some(user) = lock.iter_mut().find(|u| u.id == req.id) {
-            if let Some(name) = req.name {
-                user.name = name;
-            }
-            if let Some(username) = req.username {
-                user.username = username;
-            }
-            if let Some(email) = req.email {
-                user.email = email;
-            }
-            return Ok(Response::new(UserResponse {
-                user: Some(user.clone()),
-            }));
-        }
-        Err(Status::not_found("User not found"))
+        request: tonic::Request<RelatedNewsRequest>,
+    ) -> std::result::Result<Response<NewsList>, Status> {
+        let meta = self.start_meta(&request);
+        let request = request.into_inner();
+        let limit = self.config.page_size(request.limit);
+        let lock = self.lock_news();
+        let source = lock
+            .iter()
+            .find(|n| n.id == request.id)
+            .ok_or_else(|| Status::not_found("News not found"))?;
+        let source_keywords = news_keywords(source);
+        let mut related: Vec<(usize, &News)> = lock
+            .iter()
+            .filter(|n| n.id != source.id && n.status() == NewsStatus::Published)
+            .map(|n| (news_keywords(n).intersection(&source_keywords).count(), n))
+            .filter(|(shared, _)| *shared > 0)
+            .collect();
+        related.sort_by(|(a, na), (b, nb)| b.cmp(a).then(na.id.cmp(&nb.id)));
+        let news = related
+            .into_iter()
+            .take(limit)
+            .map(|(_, n)| n.clone())
+            .collect();
+        Ok(Response::new(NewsList {
+            news,
+            meta: meta.map(MetaTimer::finish),
+        }))
     }
 
-    async fn delete_user(
+    async fn search_news(
         &self,
-        request: tonic::Request<UserRequest>,
-    ) -> std::result::Result<Response<UserDeleteResponse>, Status> {
+        request: tonic::Request<NewsSearchQuery>,
+    ) -> std::result::Result<Response<NewsList>, Status> {
+        let meta = self.start_meta(&request);
+        let query = request.into_inner();
+        let keywords: Vec<String> = query
+            .keywords
+            .iter()
+            .map(|k| k.trim().to_lowercase())
+            .filter(|k| !k.is_empty())
+            .collect();
+        if keywords.is_empty() {
+            return Err(Status::invalid_argument("at least one keyword is required"));
+        }
+        let news = self
+            .lock_news()
+            .iter()
+            .filter(|n| {
+                let text = format!("{}\n{}", n.title, n.body).to_lowercase();
+                let mut found = keywords.iter().map(|k| text.contains(k.as_str()));
+                if query.match_all {
+                    found.all(|f| f)
+                } else {
+                    found.any(|f| f)
+                }
+            })
+            .cloned()
+            .collect();
+        Ok(Response::new(NewsList {
+            news,
+            meta: meta.map(MetaTimer::finish),
+        }))
+    }
+
+    async fn list_news_older_than(
+        &self,
+        request: tonic::Request<OlderThanRequest>,
+    ) -> std::result::Result<Response<NewsList>, Status> {
+        let meta = self.start_meta(&request);
+        let request = request.into_inner();
+        let mut news: Vec<News> = self
+            .lock_news()
+            .iter()
+            .filter(|n| n.created_at < request.before)
+            .filter(|n| request.status.is_none_or(|status| n.status == status))
+            .cloned()
+            .collect();
+        news.sort_by_key(|n| (n.created_at, n.id));
+        Ok(Response::new(NewsList {
+            news,
+            meta: meta.map(MetaTimer::finish),
+        }))
+    }
+
+    async fn list_news_by_author(
+        &self,
+        request: tonic::Request<NewsByAuthorRequest>,
+    ) -> std::result::Result<Response<NewsPage>, Status> {
+        let meta = self.start_meta(&request);
+        let request = request.into_inner();
+        let page_size = self.config.page_size(request.page_size);
+        let lock = self.lock_news();
+        let news = lock
+            .iter()
+            .filter(|n| n.author_id == Some(request.author_id))
+            .cloned();
+        let page = paginate(news, |n| n.id, &request.page_token, page_size)?;
+        Ok(Response::new(NewsPage {
+            news: page.items,
+            next_page_token: page.next_page_token,
+            page_size: page_size as u32,
+            meta: meta.map(MetaTimer::finish),
+        }))
+    }
+
+    async fn get_news_sample(
+        &self,
+        request: tonic::Request<SampleRequest>,
+    ) -> std::result::Result<Response<NewsList>, Status> {
+        let meta = self.start_meta(&request);
+        let count = request.into_inner().count as usize;
+        let lock = self.lock_news();
+        let published: Vec<&News> = lock
+            .iter()
+            .filter(|n| n.status() == NewsStatus::Published)
+            .collect();
+        let mut rng = self.rng.lock().unwrap();
+        // `choose_multiple` samples without replacement and caps `count` at the slice length.
+        let news = published
+            .choose_multiple(&mut *rng, count)
+            .map(|n| (*n).clone())
+            .collect();
+        Ok(Response::new(NewsList {
+            news,
+            meta: meta.map(MetaTimer::finish),
+        }))
+    }
+
+    async fn get_news_feed(
+        &self,
+        request: tonic::Request<FeedRequest>,
+    ) -> std::result::Result<Response<NewsList>, Status> {
+        let meta = self.start_meta(&request);
+        let mut slots: Vec<(NewsStatus, usize)> = Vec::new();
+        for slot in request.into_inner().slots {
+            let status = slot.status();
+            match slots.iter_mut().find(|(s, _)| *s == status) {
+                Some((_, count)) => *count += slot.count as usize,
+                None => slots.push((status, slot.count as usize)),
+            }
+        }
+        let lock = self.lock_news();
+        let mut queues: Vec<_> = slots
+            .into_iter()
+            .map(|(status, count)| {
+                let mut matching: Vec<&News> =
+                    lock.iter().filter(|n| n.status() == status).collect();
+                matching.sort_by_key(|n| std::cmp::Reverse((n.updated_at, n.id)));
+                matching.truncate(count);
+                matching.into_iter()
+            })
+            .collect();
+        let mut news = Vec::new();
+        loop {
+            let taken = news.len();
+            for queue in &mut queues {
+                news.extend(queue.next().cloned());
+            }
+            if news.len() == taken {
+                break;
+            }
+        }
+        Ok(Response::new(NewsList {
+            news,
+            meta: meta.map(MetaTimer::finish),
+        }))
+    }
+
+    type SyncNewsStream =
+        Pin<Box<dyn Stream<Item = std::result::Result<SyncDelta, Status>> + Send>>;
+
+    async fn sync_news(
+        &self,
+        request: tonic::Request<Streaming<SyncEntry>>,
+    ) -> std::result::Result<Response<Self::SyncNewsStream>, Status> {
+        let mut entries = request.into_inner();
+        let mut held = HashMap::new();
+        while let Some(entry) = entries.message().await? {
+            held.insert(entry.id, entry.version);
+        }
+        let deltas = sync_deltas(&self.lock_news(), &held);
+        Ok(Response::new(Box::pin(tokio_stream::iter(
+            deltas.into_iter().map(Ok),
+        ))))
+    }
+
+    type WatchNewsStream =
+        Pin<Box<dyn Stream<Item = std::result::Result<NewsEvent, Status>> + Send>>;
+
+    async fn watch_news(
+        &self,
+        request: tonic::Request<WatchNewsRequest>,
+    ) -> std::result::Result<Response<Self::WatchNewsStream>, Status> {
+        let filter = request.into_inner();
+        let guard = self.watchers.acquire()?;
+        let events = BroadcastStream::new(self.news_events.subscribe())
+            .filter(move |event| match event {
+                Ok(event) => {
+                    let status = event.news.as_ref().map_or(0, |n| n.status);
+                    (filter.statuses.is_empty() || filter.statuses.contains(&status))
+                        && (filter.change_types.is_empty()
+                            || filter.change_types.contains(&event.change_type))
+                }
+                Err(BroadcastStreamRecvError::Lagged(_)) => true,
+            })
+            .map(move |event| {
+                // Owned by the stream, so the slot is freed once the subscriber disconnects.
+                let _guard = &guard;
+                Ok(match event {
+                    Ok(event) => event,
+                    Err(BroadcastStreamRecvError::Lagged(skipped)) => NewsEvent {
+                        lagged: skipped,
+                        ..Default::default()
+                    },
+                })
+            });
+        let Some(window) = self.config.watch_batch else {
+            return Ok(Response::new(Box::pin(events)));
+        };
+        let batches = events
+            .filter_map(std::result::Result::ok)
+            .chunks_timeout(WATCH_BATCH_MAX_EVENTS, window)
+            .map(|batched| {
+                Ok(NewsEvent {
+                    batched,
+                    ..Default::default()
+                })
+            });
+        Ok(Response::new(Box::pin(batches)))
+    }
+}
+
+#[tonic::async_trait]
+impl PostService for MyGrpcService {
+    async fn list_posts(
+        &self,
+        request: tonic::Request<PostFilter>,
+    ) -> std::result::Result<Response<PostList>, Status> {
+        let meta = self.start_meta(&request);
+        let filter = request.into_inner();
+        let page_size = self
+            .config
+            .opt_in_page_size(filter.page_size, &filter.page_token);
+        let lock = self.lock_posts();
+        let posts = lock
+            .iter()
+            .filter(|p| filter.user_id.is_none_or(|user_id| p.user_id == user_id))
+            .cloned();
+        let page = match page_size {
+            Some(page_size) => paginate_scoped(
+                posts,
+                |p| p.id,
+                &post_filter_scope(&filter),
+                &filter.page_token,
+                page_size,
+            )?,
+            None => Page {
+                items: posts.collect(),
+                next_page_token: String::new(),
+            },
+        };
+        Ok(Response::new(PostList {
+            posts: page.items,
+            next_page_token: page.next_page_token,
+            page_size: page_size.unwrap_or(0) as u32,
+            meta: meta.map(MetaTimer::finish),
+        }))
+    }
+
+    async fn get_post(
+        &self,
+        request: tonic::Request<PostRequest>,
+    ) -> std::result::Result<Response<Post>, Status> {
         let id = request.into_inner().id;
-        let mut lock = self.users.lock().unwrap();
-        let len_before = lock.len();
-        lock.retain(|u| u.id != id);
-        if lock.len() < len_before {
-            Ok(Response::new(UserDeleteResponse {
-                success: true,
-                message: "User deleted".into(),
+        let lock = self.lock_posts();
+        let post = lock.iter().find(|p| p.id == id).cloned();
+        match post {
+            Some(post) => Ok(Response::new(post)),
+            None => Err(Status::not_found("Post not found")),
+        }
+    }
+
+    async fn get_post_scoped(
+        &self,
+        request: tonic::Request<ScopedPostRequest>,
+    ) -> std::result::Result<Response<Post>, Status> {
+        let request = request.into_inner();
+        let lock = self.lock_posts();
+        lock.iter()
+            .find(|p| p.id == request.id && p.user_id == request.user_id)
+            .cloned()
+            .map(Response::new)
+            // Same message as an unknown id, so non-owners cannot probe for posts.
+            .ok_or_else(|| Status::not_found("Post not found"))
+    }
+
+    type GetPostBodyStream =
+        tokio_stream::Iter<std::vec::IntoIter<std::result::Result<PostBodyChunk, Status>>>;
+
+    async fn get_post_body(
+        &self,
+        request: tonic::Request<PostRequest>,
+    ) -> std::result::Result<Response<Self::GetPostBodyStream>, Status> {
+        let id = request.into_inner().id;
+        let body = {
+            let lock = self.lock_posts();
+            match lock.iter().find(|p| p.id == id) {
+                Some(post) => post.body.clone(),
+                None => return Err(Status::not_found("Post not found")),
+            }
+        };
+        let chunks = split_at_char_boundaries(&body, self.config.post_body_chunk_bytes)
+            .into_iter()
+            .map(|data| Ok(PostBodyChunk { data: data.into() }))
+            .collect::<Vec<_>>();
+        Ok(Response::new(tokio_stream::iter(chunks)))
+    }
+
+    async fn list_posts_since(
+        &self,
+        request: tonic::Request<ListPostsSinceRequest>,
+    ) -> std::result::Result<Response<ListPostsSinceResponse>, Status> {
+        let request = request.into_inner();
+        let page_size = self.config.page_size(request.page_size);
+        let after = request.after.unwrap_or_default();
+        let after_key = (after.updated_at, after.id);
+        let lock = self.lock_posts();
+        let mut posts: Vec<Post> = lock
+            .iter()
+            .filter(|p| (p.updated_at, p.id) > after_key)
+            .cloned()
+            .collect();
+        posts.sort_by_key(|p| (p.updated_at, p.id));
+        posts.truncate(page_size);
+        let next_cursor = posts.last().map_or(after, |p| PostCursor {
+            updated_at: p.updated_at,
+            id: p.id,
+        });
+        Ok(Response::new(ListPostsSinceResponse {
+            posts,
+            next_cursor: Some(next_cursor),
+        }))
+    }
+
+    async fn get_posts_by_ids(
+        &self,
+        request: tonic::Request<PostIds>,
+    ) -> std::result::Result<Response<PostsByIds>, Status> {
+        let ids = request.into_inner().ids;
+        let lock = self.lock_posts();
+        let posts_by_id: HashMap<i32, &Post> = lock.iter().map(|p| (p.id, p)).collect();
+        let mut seen = HashSet::new();
+        let mut response = PostsByIds::default();
+        for id in ids.into_iter().filter(|id| seen.insert(*id)) {
+            match posts_by_id.get(&id) {
+                Some(&post) => response.posts.push(post.clone()),
+                None => response.missing_ids.push(id),
+            }
+        }
+        Ok(Response::new(response))
+    }
+
+    async fn get_post_revisions(
+        &self,
+        request: tonic::Request<PostRequest>,
+    ) -> std::result::Result<Response<PostRevisions>, Status> {
+        let id = request.into_inner().id;
+        let lock = self.lock_posts();
+        if !lock.iter().any(|p| p.id == id) {
+            return Err(Status::not_found("Post not found"));
+        }
+        let revisions = self
+            .post_revisions
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|revisions| revisions.iter().cloned().collect())
+            .unwrap_or_default();
+        Ok(Response::new(PostRevisions { revisions }))
+    }
+
+    async fn get_post_word_count(
+        &self,
+        request: tonic::Request<PostRequest>,
+    ) -> std::result::Result<Response<PostWordCount>, Status> {
+        let id = request.into_inner().id;
+        let lock = self.lock_posts();
+        let post = lock
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| Status::not_found("Post not found"))?;
+        // Words are split on Unicode whitespace rather than full UAX #29 segmentation, so
+        // punctuation stays attached and scripts written without spaces count as one word.
+        Ok(Response::new(PostWordCount {
+            word_count: post.body.split_whitespace().count() as u32,
+            char_count: post.body.chars().count() as u32,
+        }))
+    }
+
+    async fn search_posts_by_prefix(
+        &self,
+        request: tonic::Request<PostPrefixQuery>,
+    ) -> std::result::Result<Response<PostList>, Status> {
+        let meta = self.start_meta(&request);
+        let query = request.into_inner();
+        let limit = self.config.page_size(query.limit);
+        let prefix = query.prefix.to_lowercase();
+        let lock = self.lock_posts();
+        let mut matches: Vec<(String, Post)> = lock
+            .iter()
+            .map(|p| (p.title.to_lowercase(), p))
+            .filter(|(title, _)| title.starts_with(&prefix))
+            .map(|(title, p)| (title, p.clone()))
+            .collect();
+        matches.sort_by(|(a, pa), (b, pb)| a.cmp(b).then(pa.id.cmp(&pb.id)));
+        let posts = matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, post)| post)
+            .collect();
+        Ok(Response::new(PostList {
+            posts,
+            next_page_token: String::new(),
+            page_size: limit as u32,
+            meta: meta.map(MetaTimer::finish),
+        }))
+    }
+
+    async fn create_post(
+        &self,
+        request: tonic::Request<Post>,
+    ) -> std::result::Result<Response<PostResponse>, Status> {
+        let mut post = request.into_inner();
+        let validate_only = std::mem::take(&mut post.validate_only);
+        self.check_client_id(post.id)?;
+        self.check_body_len(&post.body, self.config.create_body_limit())?;
+        // Held until the post is stored so the author cannot be deleted in between.
+        let users = self.lock_users();
+        if !self.config.skip_author_check && !users.iter().any(|u| u.id == post.user_id) {
+            return Err(Status::failed_precondition("author does not exist"));
+        }
+        let mut lock = self.lock_posts();
+        self.check_capacity(lock.len())?;
+        let new_id = lock.iter().map(|p| p.id).max().unwrap_or(0) + 1;
+        post.id = new_id;
+        post.created_at = now_millis();
+        post.updated_at = post.created_at;
+        if validate_only {
+            return Ok(Response::new(PostResponse { post: Some(post) }));
+        }
+        lock.push(post.clone());
+        let before = self.post_before(post.id, None);
+        let sequence = self.publish_change(
+            EntityType::Post,
+            post.id,
+            Some(post.user_id),
+            ChangeType::Created,
+        );
+        self.undo.record(sequence, vec![before]);
+        Ok(Response::new(PostResponse { post: Some(post) }))
+    }
+
+    async fn update_post(
+        &self,
+        request: tonic::Request<Post>,
+    ) -> std::result::Result<Response<PostResponse>, Status> {
+        let client_id = request.extensions().get::<ClientId>().cloned();
+        let mut post_update = request.into_inner();
+        let validate_only = std::mem::take(&mut post_update.validate_only);
+        self.check_body_len(&post_update.body, self.config.update_body_limit())?;
+        let mut lock = self.lock_posts();
+        if let Some(post) = lock.iter_mut().find(|p| p.id == post_update.id) {
+            post_update.created_at = post.created_at;
+            post_update.updated_at = now_millis();
+            if validate_only {
+                return Ok(Response::new(PostResponse {
+                    post: Some(post_update),
+                }));
+            }
+            let before = self.post_before(post.id, Some(post.clone()));
+            self.record_post_revision(post, &post_update, client_id.as_ref());
+            *post = post_update.clone();
+            let sequence = self.publish_change(
+                EntityType::Post,
+                post_update.id,
+                Some(post_update.user_id),
+                ChangeType::Updated,
+            );
+            self.undo.record(sequence, vec![before]);
+            return Ok(Response::new(PostResponse {
+                post: Some(post_update),
+            }));
+        }
+        Err(Status::not_found("Post not found"))
+    }
+
+    async fn delete_post(
+        &self,
+        request: tonic::Request<PostRequest>,
+    ) -> std::result::Result<Response<PostDeleteResponse>, Status> {
+        let id = request.into_inner().id;
+        let mut lock = self.lock_posts();
+        match lock.iter().position(|p| p.id == id) {
+            Some(index) => {
+                let post = lock.remove(index);
+                let before = self.post_before(id, Some(post.clone()));
+                self.post_revisions.lock().unwrap().remove(&id);
+                let sequence = self.publish_change(
+                    EntityType::Post,
+                    id,
+                    Some(post.user_id),
+                    ChangeType::Deleted,
+                );
+                self.undo.record(sequence, vec![before]);
+                Ok(Response::new(PostDeleteResponse {
+                    success: true,
+                    message: "Post deleted".into(),
+                }))
+            }
+            None => Err(Status::not_found("Post not found")),
+        }
+    }
+
+    async fn delete_posts_by_user(
+        &self,
+        request: tonic::Request<DeletePostsByUserRequest>,
+    ) -> std::result::Result<Response<DeletePostsByUserResponse>, Status> {
+        let user_id = request.into_inner().user_id;
+        let mut lock = self.lock_posts();
+        let (deleted, kept): (Vec<Post>, Vec<Post>) =
+            lock.drain(..).partition(|p| p.user_id == user_id);
+        *lock = kept;
+        let mut before = Vec::new();
+        let mut sequence = 0;
+        for post in &deleted {
+            before.push(self.post_before(post.id, Some(post.clone())));
+            self.post_revisions.lock().unwrap().remove(&post.id);
+            sequence = self.publish_change(
+                EntityType::Post,
+                post.id,
+                Some(user_id),
+                ChangeType::Deleted,
+            );
+        }
+        self.undo.record(sequence, before);
+        Ok(Response::new(DeletePostsByUserResponse {
+            deleted: deleted.len() as i32,
+        }))
+    }
+
+    async fn transfer_post(
+        &self,
+        request: tonic::Request<TransferPostRequest>,
+    ) -> std::result::Result<Response<Post>, Status> {
+        // Both are unset when authentication is disabled, in which case every caller may
+        // transfer, as with every other RPC.
+        let client_id = request.extensions().get::<ClientId>().cloned();
+        let role = request.extensions().get::<Role>().copied();
+        let request = request.into_inner();
+        let users = self.lock_users();
+        if !self.config.skip_author_check && !users.iter().any(|u| u.id == request.new_user_id) {
+            return Err(Status::failed_precondition("author does not exist"));
+        }
+        let mut posts = self.lock_posts();
+        let post = posts
+            .iter_mut()
+            .find(|p| p.id == request.post_id)
+            .ok_or_else(|| Status::not_found("Post not found"))?;
+        if let Some(ClientId(client_id)) = &client_id {
+            let is_owner = self.config.client_users.get(client_id) == Some(&post.user_id);
+            if !is_owner && role != Some(Role::Admin) {
+                return Err(Status::permission_denied(
+                    "only the post's owner or an admin may transfer it",
+                ));
+            }
+        }
+        let undo_before = self.post_before(post.id, Some(post.clone()));
+        let before = post.clone();
+        post.user_id = request.new_user_id;
+        post.updated_at = now_millis();
+        self.record_post_revision(&before, post, client_id.as_ref());
+        let sequence = self.publish_change(
+            EntityType::Post,
+            post.id,
+            Some(post.user_id),
+            ChangeType::Updated,
+        );
+        self.undo.record(sequence, vec![undo_before]);
+        Ok(Response::new(post.clone()))
+    }
+
+    async fn move_user_posts(
+        &self,
+        request: tonic::Request<MoveUserPostsRequest>,
+    ) -> std::result::Result<Response<MoveUserPostsResponse>, Status> {
+        let client_id = request.extensions().get::<ClientId>().cloned();
+        let request = request.into_inner();
+        let users = self.lock_users();
+        if !self.config.skip_author_check && !users.iter().any(|u| u.id == request.target_user_id) {
+            return Err(Status::failed_precondition("author does not exist"));
+        }
+        let mut posts = self.lock_posts();
+        let mut undo_before = Vec::new();
+        let mut sequence = 0;
+        let results = request
+            .post_ids
+            .into_iter()
+            .map(|post_id| {
+                let result = match posts.iter_mut().find(|p| p.id == post_id) {
+                    None => move_post_result::Result::Error("Post not found".into()),
+                    Some(post)
+                        if request
+                            .source_user_id
+                            .is_some_and(|source| post.user_id != source) =>
+                    {
+                        move_post_result::Result::Error("post belongs to another user".into())
+                    }
+                    Some(post) => {
+                        undo_before.push(self.post_before(post_id, Some(post.clone())));
+                        let before = post.clone();
+                        post.user_id = request.target_user_id;
+                        post.updated_at = now_millis();
+                        self.record_post_revision(&before, post, client_id.as_ref());
+                        sequence = self.publish_change(
+                            EntityType::Post,
+                            post_id,
+                            Some(post.user_id),
+                            ChangeType::Updated,
+                        );
+                        move_post_result::Result::Post(post.clone())
+                    }
+                };
+                MovePostResult {
+                    post_id,
+                    result: Some(result),
+                }
+            })
+            .collect();
+        self.undo.record(sequence, undo_before);
+        Ok(Response::new(MoveUserPostsResponse { results }))
+    }
+}
+
+#[tonic::async_trait]
+impl UserService for MyGrpcService {
+    async fn list_users(
+        &self,
+        request: tonic::Request<UserFilter>,
+    ) -> std::result::Result<Response<UserList>, Status> {
+        let meta = self.start_meta(&request);
+        let filter = request.into_inner();
+        let page_size = self
+            .config
+            .opt_in_page_size(filter.page_size, &filter.page_token);
+        let lock = self.lock_users();
+        let users = lock
+            .iter()
+            .filter(|u| filter.id.is_empty() || filter.id.contains(&u.id))
+            .cloned();
+        let page = match page_size {
+            Some(page_size) => paginate(users, |u| u.id, &filter.page_token, page_size)?,
+            None => Page {
+                items: users.collect(),
+                next_page_token: String::new(),
+            },
+        };
+        Ok(Response::new(UserList {
+            users: page.items,
+            next_page_token: page.next_page_token,
+            page_size: page_size.unwrap_or(0) as u32,
+            meta: meta.map(MetaTimer::finish),
+        }))
+    }
+
+    async fn get_user_login_hint(
+        &self,
+        request: tonic::Request<UserRequest>,
+    ) -> std::result::Result<Response<LoginHint>, Status> {
+        // Callers need no API key, so the budget is per peer address instead of per client.
+        let peer = request
+            .extensions()
+            .get::<ConnectionInfo>()
+            .and_then(|info| info.peer)
+            .map(|peer| peer.ip());
+        if !self.login_hints.check(peer) {
+            return Err(Status::resource_exhausted("too many login hint requests"));
+        }
+        let id = request.into_inner().id;
+        let lock = self.lock_users();
+        let user = lock
+            .iter()
+            .find(|u| u.id == id)
+            .ok_or_else(|| Status::not_found("User not found"))?;
+        // Extensions such as "x56442" are dropped so the hint ends in the number itself.
+        let phone = user
+            .phone
+            .split(['x', 'X'])
+            .next()
+            .unwrap_or_default()
+            .trim();
+        Ok(Response::new(LoginHint {
+            masked_email: redact::email(&user.email),
+            masked_phone: redact::phone(phone),
+        }))
+    }
+
+    async fn get_user(
+        &self,
+        request: tonic::Request<UserRequest>,
+    ) -> std::result::Result<Response<User>, Status> {
+        let id = request.into_inner().id;
+        let lock = self.lock_users();
+        let user = lock.iter().find(|u| u.id == id).cloned();
+        match user {
+            Some(user) => Ok(Response::new(user)),
+            None => Err(Status::not_found("User not found")),
+        }
+    }
+
+    async fn get_user_with_posts_paginated(
+        &self,
+        request: tonic::Request<UserPostsRequest>,
+    ) -> std::result::Result<Response<UserWithPosts>, Status> {
+        let request = request.into_inner();
+        let page_size = self.config.page_size(request.page_size);
+        let users = self.lock_users();
+        let posts = self.lock_posts();
+        let user = users
+            .iter()
+            .find(|u| u.id == request.id)
+            .cloned()
+            .ok_or_else(|| Status::not_found("User not found"))?;
+        let page = paginate_scoped(
+            posts.iter().filter(|p| p.user_id == request.id).cloned(),
+            |p| p.id,
+            &format!("user-{}", request.id),
+            &request.page_token,
+            page_size,
+        )?;
+        Ok(Response::new(UserWithPosts {
+            user: Some(user),
+            posts: page.items,
+            next_page_token: page.next_page_token,
+            page_size: page_size as u32,
+        }))
+    }
+
+    async fn get_user_profile(
+        &self,
+        request: tonic::Request<UserRequest>,
+    ) -> std::result::Result<Response<UserProfile>, Status> {
+        let id = request.into_inner().id;
+        let users = self.lock_users();
+        let posts = self.lock_posts();
+        let user = users
+            .iter()
+            .find(|u| u.id == id)
+            .cloned()
+            .ok_or_else(|| Status::not_found("User not found"))?;
+        let user_posts = posts.iter().filter(|p| p.user_id == id);
+        let post_count = user_posts.clone().count() as i64;
+        let latest_post = user_posts.max_by_key(|p| (p.created_at, p.id)).cloned();
+        Ok(Response::new(UserProfile {
+            user: Some(user),
+            post_count,
+            latest_post,
+        }))
+    }
+
+    async fn list_posts_with_authors(
+        &self,
+        request: tonic::Request<PostFilter>,
+    ) -> std::result::Result<Response<PostsWithAuthors>, Status> {
+        let meta = self.start_meta(&request);
+        let filter = request.into_inner();
+        let page_size = self.config.page_size(filter.page_size);
+        let users = self.lock_users();
+        let posts = self.lock_posts();
+        let page = paginate_scoped(
+            posts
+                .iter()
+                .filter(|p| filter.user_id.is_none_or(|user_id| p.user_id == user_id))
+                .cloned(),
+            |p| p.id,
+            &post_filter_scope(&filter),
+            &filter.page_token,
+            page_size,
+        )?;
+        // Built once so each post's author is a map lookup rather than a scan.
+        let authors: HashMap<i32, &User> = users.iter().map(|u| (u.id, u)).collect();
+        let posts = page
+            .items
+            .into_iter()
+            .map(|post| PostWithAuthor {
+                author: authors.get(&post.user_id).map(|&u| u.clone()),
+                post: Some(post),
+            })
+            .collect();
+        Ok(Response::new(PostsWithAuthors {
+            posts,
+            next_page_token: page.next_page_token,
+            page_size: page_size as u32,
+            meta: meta.map(MetaTimer::finish),
+        }))
+    }
+
+    async fn get_user_activity(
+        &self,
+        request: tonic::Request<UserActivityRequest>,
+    ) -> std::result::Result<Response<UserActivity>, Status> {
+        let request = request.into_inner();
+        let limit = self.config.page_size(request.limit);
+        let events = self
+            .changes
+            .recent()
+            .into_iter()
+            .filter(|event| event.user_id == Some(request.user_id))
+            .take(limit)
+            .collect();
+        Ok(Response::new(UserActivity { events }))
+    }
+
+    async fn list_users_created_between(
+        &self,
+        request: tonic::Request<CreatedBetweenRequest>,
+    ) -> std::result::Result<Response<UserList>, Status> {
+        let meta = self.start_meta(&request);
+        let range = request.into_inner();
+        if range.start > range.end {
+            return Err(Status::invalid_argument("start must not be after end"));
+        }
+        let lock = self.lock_users();
+        let mut users: Vec<User> = lock
+            .iter()
+            .filter(|u| (range.start..=range.end).contains(&u.created_at))
+            .cloned()
+            .collect();
+        users.sort_by_key(|u| (u.created_at, u.id));
+        Ok(Response::new(UserList {
+            users,
+            meta: meta.map(MetaTimer::finish),
+            ..Default::default()
+        }))
+    }
+
+    async fn get_users_by_email_domain(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<EmailDomainCounts>, Status> {
+        let lock = self.lock_users();
+        let mut counts = HashMap::new();
+        for user in lock.iter() {
+            let domain = email_domain(&user.email).unwrap_or_else(|| "unknown".into());
+            *counts.entry(domain).or_insert(0) += 1;
+        }
+        Ok(Response::new(EmailDomainCounts { counts }))
+    }
+
+    async fn create_user(
+        &self,
+        request: tonic::Request<User>,
+    ) -> std::result::Result<Response<UserResponse>, Status> {
+        let mut user = request.into_inner();
+        let validate_only = std::mem::take(&mut user.validate_only);
+        self.check_client_id(user.id)?;
+        user.email = normalize_email(&user.email);
+        if user.company.is_none() {
+            user.company = self
+                .config
+                .default_company_name
+                .clone()
+                .map(|name| Company {
+                    name,
+                    ..Default::default()
+                });
+        }
+        validate_user(&user)?;
+        let mut lock = self.lock_users();
+        self.check_capacity(lock.len())?;
+        let new_id = lock.iter().map(|u| u.id).max().unwrap_or(0) + 1;
+        user.id = new_id;
+        user.created_at = now_millis();
+        user.updated_at = user.created_at;
+        if validate_only {
+            return Ok(Response::new(UserResponse { user: Some(user) }));
+        }
+        lock.push(user.clone());
+        let sequence = self.publish_change(
+            EntityType::User,
+            user.id,
+            Some(user.id),
+            ChangeType::Created,
+        );
+        self.undo
+            .record(sequence, vec![Before::User(user.id, None)]);
+        self.log_user("created", &user);
+        Ok(Response::new(UserResponse { user: Some(user) }))
+    }
+
+    async fn patch_user(
+        &self,
+        request: tonic::Request<PatchUserRequest>,
+    ) -> std::result::Result<Response<UserResponse>, Status> {
+        let req = request.into_inner();
+        let validate_only = req.validate_only;
+        let mut lock = self.lock_users();
+        let before = lock.iter().find(|u| u.id == req.id).cloned();
+        let user = apply_user_patch(&mut lock, req)?;
+        if !validate_only {
+            let sequence = self.publish_change(
+                EntityType::User,
+                user.id,
+                Some(user.id),
+                ChangeType::Updated,
+            );
+            self.undo
+                .record(sequence, vec![Before::User(user.id, before)]);
+            self.log_user("patched", &user);
+        }
+        Ok(Response::new(UserResponse { user: Some(user) }))
+    }
+
+    async fn batch_patch_users(
+        &self,
+        request: tonic::Request<BatchPatchUsersRequest>,
+    ) -> std::result::Result<Response<BatchPatchUsersResponse>, Status> {
+        let patches = request.into_inner().patches;
+        let mut lock = self.lock_users();
+        let mut undo_before = Vec::new();
+        let mut sequence = 0;
+        let results = patches
+            .into_iter()
+            .map(|patch| {
+                let id = patch.id;
+                let validate_only = patch.validate_only;
+                let before = lock.iter().find(|u| u.id == id).cloned();
+                let result = match apply_user_patch(&mut lock, patch) {
+                    Ok(user) => {
+                        if !validate_only {
+                            undo_before.push(Before::User(id, before));
+                            sequence = self.publish_change(
+                                EntityType::User,
+                                id,
+                                Some(id),
+                                ChangeType::Updated,
+                            );
+                            self.log_user("patched", &user);
+                        }
+                        patch_user_result::Result::User(user)
+                    }
+                    Err(status) => patch_user_result::Result::Error(PatchUserError {
+                        id,
+                        reason: status.message().into(),
+                    }),
+                };
+                PatchUserResult {
+                    result: Some(result),
+                }
+            })
+            .collect();
+        self.undo.record(sequence, undo_before);
+        Ok(Response::new(BatchPatchUsersResponse { results }))
+    }
+
+    async fn delete_user(
+        &self,
+        request: tonic::Request<UserRequest>,
+    ) -> std::result::Result<Response<UserDeleteResponse>, Status> {
+        let id = request.into_inner().id;
+        let mut lock = self.lock_users();
+        if let Some(index) = lock.iter().position(|u| u.id == id) {
+            let user = lock.remove(index);
+            self.log_user("deleted", &user);
+            let sequence = self.publish_change(EntityType::User, id, Some(id), ChangeType::Deleted);
+            self.undo
+                .record(sequence, vec![Before::User(id, Some(user))]);
+            Ok(Response::new(UserDeleteResponse {
+                success: true,
+                message: "User deleted".into(),
+            }))
+        } else {
+            Err(Status::not_found("User not found"))
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl DiagnosticsService for MyGrpcService {
+    async fn get_status(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<ServerStatus>, Status> {
+        Ok(Response::new(ServerStatus {
+            version: env!("CARGO_PKG_VERSION").into(),
+            tonic_version: TONIC_VERSION.into(),
+            opentelemetry_version: OPENTELEMETRY_VERSION.into(),
+            tracing_enabled: self.tracing_enabled,
+        }))
+    }
+
+    async fn get_last_modified(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<LastModified>, Status> {
+        let news = self
+            .lock_news()
+            .iter()
+            .map(|n| (n.updated_at, EntityType::News, n.id))
+            .max_by_key(|(updated_at, _, _)| *updated_at);
+        let post = self
+            .lock_posts()
+            .iter()
+            .map(|p| (p.updated_at, EntityType::Post, p.id))
+            .max_by_key(|(updated_at, _, _)| *updated_at);
+        let user = self
+            .lock_users()
+            .iter()
+            .map(|u| (u.updated_at, EntityType::User, u.id))
+            .max_by_key(|(updated_at, _, _)| *updated_at);
+
+        let (updated_at, entity_type, id) = [news, post, user]
+            .into_iter()
+            .flatten()
+            .max_by_key(|(updated_at, _, _)| *updated_at)
+            .ok_or_else(|| Status::not_found("No entities stored"))?;
+        Ok(Response::new(LastModified {
+            updated_at,
+            entity_type: entity_type.into(),
+            id,
+        }))
+    }
+
+    async fn ping(
+        &self,
+        request: tonic::Request<PingRequest>,
+    ) -> std::result::Result<Response<PingResponse>, Status> {
+        Ok(Response::new(PingResponse {
+            nonce: request.into_inner().nonce,
+            server_time: now_millis(),
+        }))
+    }
+
+    async fn get_audit_log(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<AuditLogResponse>, Status> {
+        Ok(Response::new(AuditLogResponse {
+            entries: self.audit.entries(),
+        }))
+    }
+
+    async fn get_orphaned_posts(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<OrphanedPosts>, Status> {
+        // Lock order: users, then posts.
+        let user_ids: HashSet<i32> = self.lock_users().iter().map(|u| u.id).collect();
+        let posts = self
+            .lock_posts()
+            .iter()
+            .filter(|p| !user_ids.contains(&p.user_id))
+            .cloned()
+            .collect();
+        Ok(Response::new(OrphanedPosts { posts }))
+    }
+
+    async fn list_methods(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<MethodList>, Status> {
+        let services = selftest::service_methods(grpc::FILE_DESCRIPTOR_SET)
+            .map_err(Status::internal)?
+            .into_iter()
+            .map(|(service, methods)| ServiceMethods { service, methods })
+            .collect();
+        Ok(Response::new(MethodList { services }))
+    }
+
+    async fn get_duplicate_post_titles(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<DuplicatePostTitles>, Status> {
+        let mut groups: Vec<PostTitleGroup> = Vec::new();
+        let mut positions = HashMap::new();
+        for post in self.lock_posts().iter() {
+            let title = post.title.trim().to_lowercase();
+            let index = *positions.entry(title.clone()).or_insert_with(|| {
+                groups.push(PostTitleGroup {
+                    normalized_title: title,
+                    posts: Vec::new(),
+                });
+                groups.len() - 1
+            });
+            groups[index].posts.push(post.clone());
+        }
+        groups.retain(|group| group.posts.len() > 1);
+        Ok(Response::new(DuplicatePostTitles { groups }))
+    }
+
+    async fn undo_last_change(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<UndoResponse>, Status> {
+        // Every store is locked before popping, so no mutation can slip in between and the
+        // undo log cannot be read out of order. Lock order: news, then users, then posts.
+        let mut news = self.lock_news();
+        let mut users = self.lock_users();
+        let mut posts = self.lock_posts();
+        let entry = self
+            .undo
+            .pop()
+            .ok_or_else(|| Status::failed_precondition("no change to undo"))?;
+        // Reverted newest first, so an entity changed twice ends up in its earliest state.
+        let changes = entry
+            .before
+            .into_iter()
+            .rev()
+            .map(|before| {
+                let (entity_type, id, change_type) = match before {
+                    Before::News { id, item, history } => {
+                        let (change_type, item) = undo::restore(&mut *news, id, item, |n| n.id);
+                        restore_history(&self.news_history, id, history);
+                        let item = item.unwrap_or(News {
+                            id,
+                            ..Default::default()
+                        });
+                        self.notify_news(change_type, item);
+                        (EntityType::News, id, change_type)
+                    }
+                    Before::Post {
+                        id,
+                        item,
+                        revisions,
+                    } => {
+                        let (change_type, post) = undo::restore(&mut *posts, id, item, |p| p.id);
+                        restore_history(&self.post_revisions, id, revisions);
+                        let user_id = post.map(|p| p.user_id);
+                        self.publish_change(EntityType::Post, id, user_id, change_type);
+                        (EntityType::Post, id, change_type)
+                    }
+                    Before::User(id, before) => {
+                        let (change_type, _) = undo::restore(&mut *users, id, before, |u| u.id);
+                        self.publish_change(EntityType::User, id, Some(id), change_type);
+                        (EntityType::User, id, change_type)
+                    }
+                };
+                UndoneChange {
+                    entity_type: entity_type.into(),
+                    id,
+                    change_type: change_type.into(),
+                }
+            })
+            .collect();
+        Ok(Response::new(UndoResponse {
+            sequence: entry.sequence,
+            changes,
+        }))
+    }
+
+    type WatchAllChangesStream =
+        Pin<Box<dyn Stream<Item = std::result::Result<ChangeEvent, Status>> + Send>>;
+
+    async fn watch_all_changes(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> std::result::Result<Response<Self::WatchAllChangesStream>, Status> {
+        let guard = self.watchers.acquire()?;
+        let events = BroadcastStream::new(self.changes.subscribe()).map(move |event| {
+            // Owned by the stream, so the slot is freed once the subscriber disconnects.
+            let _guard = &guard;
+            Ok(match event {
+                Ok(event) => event,
+                Err(BroadcastStreamRecvError::Lagged(skipped)) => ChangeEvent {
+                    lagged: skipped,
+                    ..Default::default()
+                },
+            })
+        });
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+/// Full names of the gRPC services registered by `bind()`.
+const SERVICE_NAMES: &[&str] = &[
+    <NewsServiceServer<MyGrpcService> as NamedService>::NAME,
+    <PostServiceServer<MyGrpcService> as NamedService>::NAME,
+    <UserServiceServer<MyGrpcService> as NamedService>::NAME,
+    <DiagnosticsServiceServer<MyGrpcService> as NamedService>::NAME,
+];
+
+/// Logs one structured line describing the active configuration. Secrets such as API keys
+/// are only summarized, never logged.
+fn log_startup_config(config: &Config, tracing_enabled: bool) {
+    tracing::info!(
+        tracing_enabled,
+        auth_enabled = !config.api_keys.is_empty(),
+        api_key_count = config.api_keys.len(),
+        read_only = config.read_only,
+        require_tls = config.require_tls,
+        log_redact = config.log_redact,
+        default_page_size = config.default_page_size,
+        max_page_size = config.max_page_size,
+        storage_backend = ?config.storage_backend,
+        catch_panics = config.catch_panics,
+        shutdown_timeout_ms = config.shutdown_timeout.as_millis() as u64,
+        dry_run = config.dry_run,
+        services = %SERVICE_NAMES.join(","),
+        "startup configuration"
+    );
+}
+
+/// How the server stopped.
+#[derive(Debug, PartialEq)]
+enum Drain<T> {
+    /// The server finished on its own, carrying its result.
+    Completed(T),
+    /// In-flight RPCs were still running when the drain timeout elapsed.
+    TimedOut { active: usize },
+}
+
+/// Runs `restore`, then reports the server as serving. Clients can already connect, but health
+/// checks report `NotServing` until the data is loaded.
+async fn mark_ready_after(
+    health: HealthState,
+    restore: impl Future<Output = Result<()>>,
+) -> Result<()> {
+    restore.await?;
+    health.set_ready(true);
+    tracing::info!("startup complete, reporting Serving");
+    Ok(())
+}
+
+/// Waits for `signal`, then reports `NotServing` from health checks for `grace` before calling
+/// `drain`, so load balancers stop routing here before the server stops accepting requests.
+async fn shutdown_sequence(
+    signal: impl Future<Output = ()>,
+    health: HealthState,
+    grace: std::time::Duration,
+    drain: impl FnOnce(),
+) {
+    signal.await;
+    tracing::info!(
+        grace_ms = grace.as_millis() as u64,
+        "shutdown requested, reporting NotServing"
+    );
+    health.set_ready(false);
+    tokio::time::sleep(grace).await;
+    drain();
+}
+
+/// Drives `server` until it finishes. Once `shutdown` resolves the server gets at most
+/// `timeout` more to drain in-flight RPCs before it is dropped, terminating them.
+///
+/// `shutdown` is expected to also start the server's own graceful shutdown.
+async fn drain_with_timeout<F: Future>(
+    server: F,
+    shutdown: impl Future<Output = ()>,
+    timeout: std::time::Duration,
+    in_flight: &AtomicUsize,
+) -> Drain<F::Output> {
+    tokio::pin!(server);
+    tokio::select! {
+        result = &mut server => return Drain::Completed(result),
+        () = shutdown => {}
+    }
+    match tokio::time::timeout(timeout, server).await {
+        Ok(result) => Drain::Completed(result),
+        Err(_) => {
+            let active = in_flight.load(Ordering::SeqCst);
+            tracing::warn!(
+                active,
+                timeout_ms = timeout.as_millis() as u64,
+                "drain timeout elapsed, terminating in-flight RPCs"
+            );
+            Drain::TimedOut { active }
+        }
+    }
+}
+
+/// Upper bound on events per `WatchNews` batch, flushed early once reached.
+const WATCH_BATCH_MAX_EVENTS: usize = 256;
+
+/// Header with which clients opt in to `ResponseMeta` on list responses.
+const INCLUDE_META_HEADER: &str = "x-include-meta";
+
+/// Started for a request that opted in to `ResponseMeta`; finished into the response's `meta`.
+struct MetaTimer {
+    request_id: String,
+    started_at: std::time::Instant,
+}
+
+impl MetaTimer {
+    fn finish(self) -> ResponseMeta {
+        ResponseMeta {
+            server_version: env!("CARGO_PKG_VERSION").into(),
+            request_id: self.request_id,
+            elapsed_ms: self.started_at.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// Restores the news store's invariants in place: items ordered by id, and every item holding
+/// a unique slug derived from its title. Duplicate ids are reported but left alone, since
+/// either item may be the one clients know.
+fn reindex_news(news: &mut [News]) -> ReindexReport {
+    let mut anomalies = Vec::new();
+    if news.windows(2).any(|pair| pair[0].id > pair[1].id) {
+        anomalies.push("items were out of id order".to_string());
+        news.sort_by_key(|n| n.id);
+    }
+    for pair in news.windows(2).filter(|pair| pair[0].id == pair[1].id) {
+        anomalies.push(format!("id {} is used by more than one item", pair[0].id));
+    }
+
+    // The first item, in id order, to hold a valid slug keeps it, so existing links survive.
+    let mut taken = HashSet::new();
+    let mut stale = Vec::new();
+    for (index, item) in news.iter().enumerate() {
+        let base = slug::slugify(&item.title);
+        if item.slug.is_empty() {
+            anomalies.push(format!("item {} had no slug", item.id));
+        } else if !slug::is_derived(&item.slug, &base) {
+            anomalies.push(format!(
+                "item {} had slug {:?} not derived from its title",
+                item.id, item.slug
+            ));
+        } else if !taken.insert(item.slug.clone()) {
+            anomalies.push(format!("item {} duplicated slug {:?}", item.id, item.slug));
+        } else {
+            continue;
+        }
+        stale.push(index);
+    }
+    let mut reslugged_ids = Vec::new();
+    for index in stale {
+        let item = &mut news[index];
+        item.slug = slug::unique(slug::slugify(&item.title), |candidate| {
+            taken.contains(candidate)
+        });
+        taken.insert(item.slug.clone());
+        reslugged_ids.push(item.id);
+    }
+    ReindexReport {
+        anomalies,
+        reslugged_ids,
+    }
+}
+
+/// Changes a client holding `held` (id to `updated_at`) needs to match `news`: created and
+/// updated items in id order, then deleted ids in id order.
+fn sync_deltas(news: &[News], held: &HashMap<i32, i64>) -> Vec<SyncDelta> {
+    let mut deltas: Vec<SyncDelta> = news
+        .iter()
+        .filter_map(|item| {
+            let change_type = match held.get(&item.id) {
+                None => ChangeType::Created,
+                Some(&version) if version < item.updated_at => ChangeType::Updated,
+                Some(_) => return None,
+            };
+            Some(SyncDelta {
+                change_type: change_type.into(),
+                id: item.id,
+                version: item.updated_at,
+                news: Some(item.clone()),
+            })
+        })
+        .collect();
+    let mut deleted: Vec<i32> = held
+        .keys()
+        .copied()
+        .filter(|id| !news.iter().any(|item| item.id == *id))
+        .collect();
+    deleted.sort_unstable();
+    deltas.extend(deleted.into_iter().map(|id| SyncDelta {
+        change_type: ChangeType::Deleted.into(),
+        id,
+        ..Default::default()
+    }));
+    deltas
+}
+
+/// Words shorter than this are too common to relate items by.
+const MIN_KEYWORD_LEN: usize = 3;
+
+/// Distinct lowercase words of at least `MIN_KEYWORD_LEN` characters in the title and body.
+fn news_keywords(news: &News) -> HashSet<String> {
+    news.title
+        .split(|c: char| !c.is_alphanumeric())
+        .chain(news.body.split(|c: char| !c.is_alphanumeric()))
+        .filter(|word| word.chars().count() >= MIN_KEYWORD_LEN)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Puts back the history kept for entity `id`, dropping it when there was none.
+fn restore_history<T>(
+    histories: &Mutex<HashMap<i32, VecDeque<T>>>,
+    id: i32,
+    history: Option<VecDeque<T>>,
+) {
+    let mut histories = histories.lock().unwrap();
+    match history {
+        Some(history) => histories.insert(id, history),
+        None => histories.remove(&id),
+    };
+}
+
+const MILLIS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// `YYYY-MM-DD` of the UTC day `days` after the Unix epoch, in the proleptic Gregorian
+/// calendar (Howard Hinnant's `civil_from_days`).
+fn utc_date(days: i64) -> String {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Lowercased part of `email` after the `@`, or `None` if it is not of the form `local@domain`.
+fn email_domain(email: &str) -> Option<String> {
+    let (local, domain) = email.trim().split_once('@')?;
+    if local.is_empty() || domain.is_empty() || domain.contains('@') {
+        return None;
+    }
+    Some(domain.to_lowercase())
+}
+
+/// Page token scope for a post listing, so tokens only resume the filter that issued them.
+fn post_filter_scope(filter: &PostFilter) -> String {
+    filter
+        .user_id
+        .map(|user_id| format!("user-{user_id}"))
+        .unwrap_or_default()
+}
+
+/// Current time as a Unix timestamp in milliseconds.
+fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+/// Splits `text` into chunks of at most `max_bytes` bytes without breaking a UTF-8 character.
+///
+/// A chunk only exceeds `max_bytes` when a single character is wider than the limit.
+fn split_at_char_boundaries(text: &str, max_bytes: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut end = max_bytes.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end == 0 {
+            end = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
+/// Applies the set fields of `req` to the matching user, returning the updated user.
+///
+/// The patched user is validated before being stored, and is only returned when
+/// `req.validate_only` is set.
+fn apply_user_patch(
+    users: &mut [User],
+    req: PatchUserRequest,
+) -> std::result::Result<User, Status> {
+    let user = users
+        .iter_mut()
+        .find(|u| u.id == req.id)
+        .ok_or_else(|| Status::not_found("User not found"))?;
+    let mut patched = user.clone();
+    if let Some(name) = req.name {
+        patched.name = name;
+    }
+    if let Some(username) = req.username {
+        patched.username = username;
+    }
+    if let Some(email) = req.email {
+        patched.email = normalize_email(&email);
+    }
+    validate_user(&patched)?;
+    patched.updated_at = now_millis();
+    if !req.validate_only {
+        *user = patched.clone();
+    }
+    Ok(patched)
+}
+
+static RESOURCE: Lazy<Resource> = Lazy::new(|| {
+    Resource::default().merge(&Resource::new(vec![
+        KeyValue::new(
+            opentelemetry_semantic_conventions::resource::SERVICE_NAME,
+            "rust-grpc",
+        ),
+        KeyValue::new(
+            opentelemetry_semantic_conventions::resource::SERVICE_VERSION,
+            "test",
+        ),
+    ]))
+});
+
+fn init_tracer(exporter: TracesExporter) -> Result<()> {
+    let propagators = propagation::parse_propagators(
+        &std::env::var("OTEL_PROPAGATORS").unwrap_or_else(|_| "tracecontext".into()),
+    )?;
+    global::set_text_map_propagator(propagation::composite(&propagators));
+    let ratio = telemetry::sample_ratio(std::env::var("OTEL_TRACES_SAMPLER_ARG").ok().as_deref())?;
+    let sampler = ErrorAwareSampler::new(Sampler::ParentBased(Box::new(
+        Sampler::TraceIdRatioBased(ratio),
+    )));
+
+    let provider = match exporter {
+        TracesExporter::Otlp => otlp_provider(sampler)?,
+        TracesExporter::Stdout => opentelemetry_sdk::trace::TracerProvider::builder()
+            .with_simple_exporter(telemetry::StdoutExporter)
+            .with_config(
+                opentelemetry_sdk::trace::config()
+                    .with_resource(RESOURCE.clone())
+                    .with_sampler(sampler),
+            )
+            .build(),
+        TracesExporter::None => return Ok(()),
+    };
+
+    let tracer = provider.tracer("tracing");
+    let trace_layer = tracing_opentelemetry::layer()
+        .with_location(false)
+        .with_threads(false)
+        .with_tracer(tracer);
+
+    let subscriber = tracing_subscriber::registry().with(trace_layer);
+
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    global::set_tracer_provider(provider);
+
+    Ok(())
+}
+
+fn otlp_provider(sampler: ErrorAwareSampler) -> Result<opentelemetry_sdk::trace::TracerProvider> {
+    static TELEMETRY_URL: &str = "https://api.honeycomb.io:443";
+    let headers = HeaderMap::from_iter([(
+        HeaderName::from_static("x-honeycomb-team"),
+        HeaderValue::from_str(&std::env::var("HONEYCOMB_API_KEY")?)?,
+    )]);
+
+    let otlp_exporter = opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(TELEMETRY_URL)
+        .with_metadata(MetadataMap::from_headers(headers));
+
+    let provider = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(otlp_exporter)
+        .with_trace_config(
+            opentelemetry_sdk::trace::config()
+                .with_resource(RESOURCE.clone())
+                .with_sampler(sampler),
+        )
+        .install_batch(runtime::Tokio)?
+        .provider()
+        .ok_or(TraceError::Other(
+            anyhow!("Failed to instantiate OTLP provider").into(),
+        ))?;
+
+    Ok(provider)
+}
+
+/// Enables each of `encodings` for requests and responses of a generated service server.
+macro_rules! with_compression {
+    ($server:expr, $encodings:expr) => {
+        $encodings.iter().fold($server, |server, &encoding| {
+            server.accept_compressed(encoding).send_compressed(encoding)
+        })
+    };
+}
+
+#[shuttle_runtime::main]
+async fn shuttle_main() -> Result<impl Service, shuttle_runtime::Error> {
+    let exporter =
+        telemetry::traces_exporter(std::env::var("OTEL_TRACES_EXPORTER").ok().as_deref())?;
+    let tracing_enabled = match exporter {
+        // OTLP stays off without credentials so local runs work without a collector.
+        TracesExporter::Otlp => std::env::var("HONEYCOMB_API_KEY").is_ok(),
+        TracesExporter::Stdout => true,
+        TracesExporter::None => false,
+    };
+    if tracing_enabled {
+        init_tracer(exporter)?;
+    }
+
+    let config = Config::from_env()?;
+    if config.startup_selftest {
+        selftest::check_descriptor_set(grpc::FILE_DESCRIPTOR_SET).map_err(|e| {
+            tracing::error!(error = %e, "startup self-test failed");
+            shuttle_runtime::Error::Custom(anyhow!("startup self-test failed: {e}"))
+        })?;
+    }
+
+    // The snapshot, if any, is restored once `bind()` is serving health checks.
+    Ok(MyGrpcService {
+        tracing_enabled,
+        ..MyGrpcService::from_config(config)
+    })
+}
+
+#[async_trait::async_trait]
+impl Service for MyGrpcService {
+    async fn bind(mut self, addr: std::net::SocketAddr) -> Result<(), shuttle_runtime::Error> {
+        let service = tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(grpc::FILE_DESCRIPTOR_SET)
+            .build()
+            .unwrap();
+
+        log_startup_config(&self.config, self.tracing_enabled);
+
+        let health = self.health.clone();
+        let dry_run = self.config.dry_run;
+        let monitor = self.clone();
+        let snapshotter = self.clone();
+        let restorer = self.clone();
+        let purger = self.clone();
+        let shutdown_timeout = self.config.shutdown_timeout;
+        let shutdown_health_grace = self.config.shutdown_health_grace;
+        let max_connections = self.config.max_connections;
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let access_log = match &self.config.access_log_path {
+            Some(path) => Some(Arc::new(AccessLog::open(path).map_err(|e| {
+                shuttle_runtime::Error::Custom(anyhow!(
+                    "failed to open access log {}: {e}",
+                    path.display()
+                ))
+            })?)),
+            None => None,
+        };
+
+        let algos = self.config.compression_algos.clone();
+        let tonic_service = TonicServer::builder()
+            .layer(InFlightLayer::new(in_flight.clone()))
+            .layer(RequestIdLayer::new(self.config.request_id_header.clone()))
+            .layer(AccessLogLayer::new(access_log))
+            .layer(GrpcWebContentTypeLayer::new(
+                self.config.enforce_grpc_web_content_type,
+            ))
+            // Translates grpc-web before any limits apply, and encodes their rejections back.
+            .layer(tonic_web::GrpcWebLayer::new())
+            .layer(MetadataLimitLayer::new(
+                self.config.max_metadata_entries,
+                self.config.max_metadata_bytes,
+            ))
+            // Must run before OtelGrpcLayer extracts the trace context.
+            .layer(TraceTrustLayer::new(
+                self.config.trusted_trace_peers.clone(),
+            ))
+            .layer(server::OtelGrpcLayer::default())
+            // Inside OtelGrpcLayer, so error spans join the request's trace.
+            .layer(ErrorMetricsLayer::new(self.metrics.clone()))
+            .layer(AuthLayer::new(
+                self.config.api_keys.clone(),
+                self.config.api_key_roles.clone(),
+            ))
+            .layer(MinClientVersionLayer::new(
+                self.config.min_client_version.clone(),
+                self.config.allow_missing_client_version,
+            ))
+            .layer(TlsOnlyLayer::new(self.config.require_tls))
+            .layer(ReadOnlyLayer::new(self.config.read_only))
+            .layer(RequestSizeLayer::new(
+                self.metrics.clone(),
+                self.config.request_bytes_soft_limit,
+            ))
+            .layer(ChaosLayer::new(
+                self.config.chaos_mode,
+                self.config.chaos_fraction,
+                self.config.chaos_max_latency,
+                self.rng.clone(),
+            ))
+            .layer(ConcurrencyLayer::new(
+                self.metrics.clone(),
+                self.config.max_concurrent_requests,
+            ))
+            .layer(CompressionLayer::new(
+                self.config.compression,
+                self.config.compression_min_bytes,
+            ))
+            .layer(MethodTimeoutLayer::new(
+                self.config.request_timeout,
+                self.config.method_timeouts.clone(),
+            ))
+            .layer(CircuitBreakerLayer::new(
+                self.config.cb_failure_threshold.map(|threshold| {
+                    Arc::new(CircuitBreaker::new(threshold, self.config.cb_reset))
+                }),
+            ))
+            .layer(CatchPanicLayer::new(self.config.catch_panics))
+            // Inside CatchPanicLayer, whose spawned task would not see the audit task-local.
+            .layer(AuditLayer)
+            .add_service(with_compression!(
+                NewsServiceServer::new(self.clone()),
+                algos
+            ))
+            .add_service(with_compression!(
+                PostServiceServer::new(self.clone()),
+                algos
+            ))
+            .add_service(with_compression!(
+                UserServiceServer::new(self.clone()),
+                algos
+            ))
+            .add_service(with_compression!(
+                DiagnosticsServiceServer::new(self),
+                algos
+            ))
+            .add_service(health.server())
+            .add_service(service)
+            .into_service();
+        let make_svc = make_service_fn(move |conn: &LimitedConn| {
+            // This listener is plaintext; TLS, if any, is terminated before it.
+            let info = ConnectionInfo {
+                tls: false,
+                peer: Some(conn.remote_addr()),
+            };
+            let service = tonic_service.clone().map_request(
+                move |mut request: hyper::Request<hyper::Body>| {
+                    request.extensions_mut().insert(info);
+                    request
+                },
+            );
+            async move { Ok::<_, std::convert::Infallible>(service) }
+        });
+
+        if dry_run {
+            tracing::info!(%addr, "dry run: server built, not binding");
+            return Ok(());
+        }
+
+        println!("NewsService server listening on {}", addr);
+        if purger.config.soft_delete_retention.is_some() {
+            tokio::spawn(purger.purge_soft_deleted_periodically());
+        }
+        tokio::spawn(monitor.monitor_health());
+        let (drain_tx, mut drain_rx) = tokio::sync::watch::channel(());
+        let incoming = AddrIncoming::bind(&addr)
+            .map_err(|e| shuttle_runtime::Error::Custom(anyhow!("failed to bind {addr}: {e}")))?;
+        let incoming = LimitedIncoming::new(incoming, max_connections);
+        let server = hyper::Server::builder(incoming)
+            .serve(make_svc)
+            .with_graceful_shutdown(async move {
+                let _ = drain_rx.changed().await;
+            });
+        // Restoring a large snapshot can be slow, so it runs while the server already answers
+        // health checks; a failed restore stops the server.
+        let restore =
+            async move { tokio::task::spawn_blocking(move || restorer.restore_snapshot()).await? };
+        let startup = mark_ready_after(health.clone(), restore);
+        let server = async move {
+            tokio::try_join!(async { server.await.map_err(anyhow::Error::from) }, startup)
+        };
+        let shutdown = shutdown_sequence(
+            async {
+                let _ = tokio::signal::ctrl_c().await;
+            },
+            health,
+            shutdown_health_grace,
+            move || {
+                tracing::info!("draining in-flight RPCs");
+                let _ = drain_tx.send(());
+            },
+        );
+        match drain_with_timeout(server, shutdown, shutdown_timeout, &in_flight).await {
+            Drain::Completed(result) => {
+                result.map_err(shuttle_runtime::Error::Custom)?;
+                snapshotter
+                    .save_snapshot()
+                    .map_err(shuttle_runtime::Error::Custom)
+            }
+            Drain::TimedOut { active } => Err(shuttle_runtime::Error::Custom(anyhow!(
+                "terminated {active} in-flight RPCs after the drain timeout"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+    use config::SnapshotFormat;
+    use grpc::news::FeedSlot;
+    use tonic_health::pb::health_check_response::ServingStatus;
+    use tonic_types::StatusExt;
+
+    #[tokio::test]
+    async fn batch_patch_users_reports_mixed_results() {
+        let service = MyGrpcService::new();
+        let request = BatchPatchUsersRequest {
+            patches: vec![
+                PatchUserRequest {
+                    id: 1,
+                    name: Some("Patched".into()),
+                    ..Default::default()
+                },
+                PatchUserRequest {
+                    id: 42,
+                    name: Some("Missing".into()),
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let results = service
+            .batch_patch_users(tonic::Request::new(request))
+            .await
+            .unwrap()
+            .into_inner()
+            .results;
+
+        assert_eq!(results.len(), 2);
+        match &results[0].result {
+            Some(patch_user_result::Result::User(user)) => assert_eq!(user.name, "Patched"),
+            other => panic!("expected patched user, got {other:?}"),
+        }
+        match &results[1].result {
+            Some(patch_user_result::Result::Error(err)) => {
+                assert_eq!(err.id, 42);
+                assert_eq!(err.reason, "User not found");
+            }
+            other => panic!("expected error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_status_reports_tracing_disabled_without_otlp_config() {
+        let service = MyGrpcService::new();
+
+        let status = service
+            .get_status(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert!(!status.tracing_enabled);
+        assert_eq!(status.tonic_version, TONIC_VERSION);
+        assert!(!status.opentelemetry_version.is_empty());
+    }
+
+    #[tokio::test]
+    async fn list_posts_uses_default_page_size() {
+        let service = MyGrpcService {
+            config: Arc::new(Config {
+                default_page_size: 1,
+                max_page_size: 2,
+                ..Config::default()
+            }),
+            ..MyGrpcService::new()
+        };
+        service.posts.lock().unwrap().push(Post {
+            id: 3,
+            user_id: 1,
+            ..Default::default()
+        });
+
+        // A token alone opts into pagination; "0" starts before the first post.
+        let first = service
+            .list_posts(tonic::Request::new(PostFilter {
+                page_token: "0".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(first.page_size, 1);
+        assert_eq!(first.posts.len(), 1);
+        assert_eq!(first.next_page_token, "1");
+
+        let second = service
+            .list_posts(tonic::Request::new(PostFilter {
+                page_token: first.next_page_token,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(second.page_size, 1);
+        assert_eq!(second.posts[0].id, 2);
+        assert_eq!(second.next_page_token, "2");
+
+        let clamped = service
+            .list_posts(tonic::Request::new(PostFilter {
+                page_size: 100,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(clamped.page_size, 2);
+        assert_eq!(
+            clamped.posts.iter().map(|p| p.id).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(clamped.next_page_token, "2");
+    }
+
+    #[tokio::test]
+    async fn list_posts_and_users_return_everything_without_page_size_or_token() {
+        let service = MyGrpcService {
+            config: Arc::new(Config {
+                default_page_size: 1,
+                ..Config::default()
+            }),
+            ..MyGrpcService::new()
+        };
+        service.users.lock().unwrap().push(User {
+            id: 2,
+            ..Default::default()
+        });
+
+        let posts = service
+            .list_posts(tonic::Request::new(PostFilter::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(posts.posts.iter().map(|p| p.id).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(posts.page_size, 0);
+        assert!(posts.next_page_token.is_empty());
+
+        let users = service
+            .list_users(tonic::Request::new(UserFilter::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(users.users.iter().map(|u| u.id).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(users.page_size, 0);
+        assert!(users.next_page_token.is_empty());
+    }
+
+    #[tokio::test]
+    async fn list_posts_clamps_page_size_to_max() {
+        let service = MyGrpcService {
+            config: Arc::new(Config {
+                default_page_size: 1,
+                max_page_size: 1,
+                ..Config::default()
+            }),
+            ..MyGrpcService::new()
+        };
+
+        let list = service
+            .list_posts(tonic::Request::new(PostFilter {
+                page_size: 100,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(list.page_size, 1);
+        assert_eq!(list.posts.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn get_post_body_chunks_reassemble_to_original() {
+        let body = "héllo wörld, ünïcödé ✓ ".repeat(20);
+        let service = MyGrpcService {
+            config: Arc::new(Config {
+                post_body_chunk_bytes: 7,
+                ..Config::default()
+            }),
+            ..MyGrpcService::new()
+        };
+        service.posts.lock().unwrap()[0].body = body.clone();
+
+        let chunks: Vec<_> = service
+            .get_post_body(tonic::Request::new(PostRequest { id: 1 }))
+            .await
+            .unwrap()
+            .into_inner()
+            .collect()
+            .await;
+
+        assert!(chunks.len() > 1);
+        let reassembled: String = chunks.into_iter().map(|c| c.unwrap().data).collect();
+        assert_eq!(reassembled, body);
+    }
+
+    #[tokio::test]
+    async fn get_post_body_missing_post_is_not_found() {
+        let service = MyGrpcService::new();
+        let err = service
+            .get_post_body(tonic::Request::new(PostRequest { id: 99 }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn watch_news_sequences_increase_by_one_per_mutation() {
+        let service = MyGrpcService::new();
+        let mut events = service
+            .watch_news(tonic::Request::new(WatchNewsRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let added = service
+            .add_news(tonic::Request::new(News {
+                title: "Breaking".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        service
+            .edit_news(tonic::Request::new(News {
+                title: "Updated".into(),
+                ..added.clone()
+            }))
+            .await
+            .unwrap();
+        service
+            .delete_news(tonic::Request::new(NewsId { id: added.id }))
+            .await
+            .unwrap();
+
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            received.push(events.next().await.unwrap().unwrap());
+        }
+
+        let sequences: Vec<_> = received.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![1, 2, 3]);
+        let change_types: Vec<_> = received.iter().map(|e| e.change_type()).collect();
+        assert_eq!(
+            change_types,
+            vec![
+                ChangeType::Created,
+                ChangeType::Updated,
+                ChangeType::Deleted
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn get_user_profile_includes_post_count_and_latest_post() {
+        let service = MyGrpcService::new();
+
+        let profile = service
+            .get_user_profile(tonic::Request::new(UserRequest { id: 1 }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(profile.user.unwrap().id, 1);
+        assert_eq!(profile.post_count, 2);
+        assert_eq!(profile.latest_post.unwrap().id, 2);
+    }
+
+    #[tokio::test]
+    async fn get_user_profile_without_posts_has_no_latest_post() {
+        let service = MyGrpcService::new();
+        let user = service
+            .create_user(tonic::Request::new(User {
+                name: "No Posts".into(),
+                email: "no.posts@example.com".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .user
+            .unwrap();
+
+        let profile = service
+            .get_user_profile(tonic::Request::new(UserRequest { id: user.id }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(profile.post_count, 0);
+        assert!(profile.latest_post.is_none());
+    }
+
+    #[tokio::test]
+    async fn add_news_validate_only_does_not_store() {
+        let service = MyGrpcService::new();
+        let count_before = service.news.lock().unwrap().len();
+
+        let news = service
+            .add_news(tonic::Request::new(News {
+                title: "Dry run".into(),
+                validate_only: true,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(news.title, "Dry run");
+        assert_eq!(service.news.lock().unwrap().len(), count_before);
+    }
+
+    #[tokio::test]
+    async fn add_news_validate_only_reports_validation_errors() {
+        let service = MyGrpcService::new();
+
+        let err = service
+            .add_news(tonic::Request::new(News {
+                validate_only: true,
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn get_last_modified_reports_latest_post_edit() {
+        let service = MyGrpcService::new();
+        let post = service
+            .update_post(tonic::Request::new(Post {
+                id: 1,
+                user_id: 1,
+                title: "Edited".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .post
+            .unwrap();
+
+        let last_modified = service
+            .get_last_modified(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(last_modified.entity_type(), EntityType::Post);
+        assert_eq!(last_modified.id, 1);
+        assert_eq!(last_modified.updated_at, post.updated_at);
+    }
+
+    #[tokio::test]
+    async fn health_reports_serving_only_after_a_slow_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            snapshot_path: Some(dir.path().join("store.snapshot")),
+            ..Config::default()
+        };
+        let saved = MyGrpcService::from_config(config.clone());
+        saved.news.lock().unwrap().truncate(1);
+        saved.save_snapshot().unwrap();
+
+        let service = MyGrpcService::from_config(config);
+        let restorer = service.clone();
+        let startup = tokio::spawn(mark_ready_after(service.health.clone(), async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            restorer.restore_snapshot()
+        }));
+
+        assert_eq!(service.health.reported().await, ServingStatus::NotServing);
+        assert_eq!(service.news.lock().unwrap().len(), 5);
+        startup.await.unwrap().unwrap();
+        assert_eq!(service.health.reported().await, ServingStatus::Serving);
+        assert_eq!(service.news.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_restore_keeps_reporting_not_serving() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.snapshot");
+        std::fs::write(&path, "not a snapshot").unwrap();
+        let service = MyGrpcService::from_config(Config {
+            snapshot_path: Some(path),
+            ..Config::default()
+        });
+        let restorer = service.clone();
+
+        let result = mark_ready_after(service.health.clone(), async move {
+            restorer.restore_snapshot()
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(service.health.reported().await, ServingStatus::NotServing);
+    }
+
+    #[tokio::test]
+    async fn get_news_with_author_resolves_author() {
+        let service = MyGrpcService::new();
+
+        let authored = service
+            .get_news_with_author(tonic::Request::new(NewsId { id: 1 }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(authored.author.unwrap().id, 1);
+
+        let anonymous = service
+            .get_news_with_author(tonic::Request::new(NewsId { id: 3 }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(anonymous.news.unwrap().id, 3);
+        assert!(anonymous.author.is_none());
+    }
+
+    #[tokio::test]
+    async fn search_posts_by_prefix_matches_case_insensitively_up_to_limit() {
+        let service = MyGrpcService::new();
+        for title in ["Rust basics", "rust advanced", "Rustacean guide", "Go tips"] {
+            service
+                .create_post(tonic::Request::new(Post {
+                    user_id: 1,
+                    title: title.into(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap();
+        }
+
+        let posts = service
+            .search_posts_by_prefix(tonic::Request::new(PostPrefixQuery {
+                prefix: "RUST".into(),
+                limit: 2,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .posts;
+
+        let titles: Vec<_> = posts.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, vec!["rust advanced", "Rust basics"]);
+    }
+
+    /// Log output written through a `tracing_subscriber::fmt` subscriber, for assertions.
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Captured {
+        fn subscriber(&self) -> impl tracing::Subscriber + Send + Sync {
+            let writer = self.clone();
+            tracing_subscriber::fmt()
+                .with_writer(move || writer.clone())
+                .with_ansi(false)
+                .finish()
+        }
+
+        fn output(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn startup_config_log_lists_services_without_secrets() {
+        let captured = Captured::default();
+        let subscriber = captured.subscriber();
+        let config = Config {
+            api_keys: [("super-secret".to_string(), "client-a".to_string())].into(),
+            ..Config::default()
+        };
+
+        tracing::subscriber::with_default(subscriber, || log_startup_config(&config, false));
+
+        let output = captured.output();
+        for service in SERVICE_NAMES {
+            assert!(output.contains(service), "missing {service} in {output}");
+        }
+        assert!(output.contains("auth_enabled=true"));
+        assert!(!output.contains("super-secret"));
+    }
+
+    #[tokio::test]
+    async fn every_user_mutation_logs_masked_contact_details() {
+        let service = MyGrpcService::from_config(Config {
+            log_redact: true,
+            ..Config::default()
+        });
+        let captured = Captured::default();
+        let _guard = tracing::subscriber::set_default(captured.subscriber());
+
+        let created = service
+            .create_user(tonic::Request::new(User {
+                name: "Ervin".into(),
+                email: "shanna@melissa.tv".into(),
+                phone: "010-692-6593".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .user
+            .unwrap();
+        service
+            .patch_user(tonic::Request::new(PatchUserRequest {
+                id: created.id,
+                name: Some("Ervin H.".into()),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        service
+            .batch_patch_users(tonic::Request::new(BatchPatchUsersRequest {
+                patches: vec![PatchUserRequest {
+                    id: 1,
+                    name: Some("Leanne".into()),
+                    ..Default::default()
+                }],
+            }))
+            .await
+            .unwrap();
+        service
+            .delete_user(tonic::Request::new(UserRequest { id: created.id }))
+            .await
+            .unwrap();
+
+        let output = captured.output();
+        for action in ["created", "patched", "deleted"] {
+            assert!(output.contains(&format!("{action} user")), "{output}");
+        }
+        assert!(output.contains("email=s***@melissa.tv"), "{output}");
+        assert!(output.contains("email=s***@april.biz"), "{output}");
+        for raw in [
+            "shanna@melissa.tv",
+            "010-692-6593",
+            "sincere@april.biz",
+            "770-736-8031",
+        ] {
+            assert!(!output.contains(raw), "{raw} in {output}");
+        }
+    }
+
+    #[tokio::test]
+    async fn get_news_history_returns_prior_versions_newest_first() {
+        let service = MyGrpcService::new();
+        for title in ["First edit", "Second edit"] {
+            service
+                .edit_news(tonic::Request::new(News {
+                    id: 1,
+                    title: title.into(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap();
+        }
+
+        let history = service
+            .get_news_history(tonic::Request::new(NewsId { id: 1 }))
+            .await
+            .unwrap()
+            .into_inner()
+            .news;
+
+        let titles: Vec<_> = history.iter().map(|n| n.title.as_str()).collect();
+        assert_eq!(titles, vec!["First edit", "Note 1"]);
+    }
+
+    #[tokio::test]
+    async fn list_users_created_between_filters_to_window() {
+        let service = MyGrpcService::new();
+        {
+            let mut users = service.users.lock().unwrap();
+            for (id, created_at) in [(2, 3_000), (3, 1_000), (4, 2_000), (5, 5_000)] {
+                users.push(User {
+                    id,
+                    created_at,
+                    ..Default::default()
+                });
+            }
+        }
+
+        let users = service
+            .list_users_created_between(tonic::Request::new(CreatedBetweenRequest {
+                start: 1_000,
+                end: 3_000,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .users;
+
+        let ids: Vec<_> = users.iter().map(|u| u.id).collect();
+        assert_eq!(ids, vec![3, 4, 2]);
+
+        let err = service
+            .list_users_created_between(tonic::Request::new(CreatedBetweenRequest {
+                start: 2,
+                end: 1,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn create_fails_once_store_reaches_max_entities() {
+        let service = MyGrpcService {
+            config: Arc::new(Config {
+                max_entities: Some(2),
+                ..Config::default()
+            }),
+            ..MyGrpcService::new()
+        };
+        let new_user = || {
+            tonic::Request::new(User {
+                name: "Capped".into(),
+                email: "capped@example.com".into(),
+                ..Default::default()
+            })
+        };
+
+        // The seed data already holds one user.
+        service.create_user(new_user()).await.unwrap();
+        let err = service.create_user(new_user()).await.unwrap_err();
+
+        assert_eq!(err.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(err.message(), "store is full");
+    }
+
+    #[tokio::test]
+    async fn delete_posts_by_user_removes_all_their_posts() {
+        let service = MyGrpcService::new();
+        let mut changes = service.changes.subscribe();
+
+        let deleted = service
+            .delete_posts_by_user(tonic::Request::new(DeletePostsByUserRequest { user_id: 1 }))
+            .await
+            .unwrap()
+            .into_inner()
+            .deleted;
+
+        assert_eq!(deleted, 2);
+        assert!(service.posts.lock().unwrap().is_empty());
+        for id in [1, 2] {
+            let event = changes.recv().await.unwrap();
+            assert_eq!(event.entity_type(), EntityType::Post);
+            assert_eq!(event.change_type(), ChangeType::Deleted);
+            assert_eq!(event.id, id);
+        }
+    }
+
+    #[tokio::test]
+    async fn get_news_sample_returns_distinct_published_items() {
+        let service = MyGrpcService {
+            rng: Arc::new(Mutex::new(StdRng::seed_from_u64(7))),
+            ..MyGrpcService::new()
+        };
+        for title in ["Extra 1", "Extra 2", "Extra 3"] {
+            service
+                .add_news(tonic::Request::new(News {
+                    title: title.into(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap();
+        }
+
+        let sample = |count| {
+            let service = service.clone();
+            async move {
+                service
+                    .get_news_sample(tonic::Request::new(SampleRequest { count }))
+                    .await
+                    .unwrap()
+                    .into_inner()
+                    .news
+            }
+        };
+
+        let news = sample(3).await;
+        let mut ids: Vec<_> = news.iter().map(|n| n.id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+        assert!(news.iter().all(|n| n.status() == NewsStatus::Published));
+
+        // Only four items are published, so larger requests are capped.
+        assert_eq!(sample(10).await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn memory_backend_yields_seeded_store() {
+        let service = MyGrpcService::from_config(Config {
+            storage_backend: StorageBackend::Memory,
+            ..Config::default()
+        });
+
+        assert_eq!(service.news.lock().unwrap().len(), 5);
+        assert_eq!(service.users.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn ping_echoes_nonce_with_current_time() {
+        let service = MyGrpcService::new();
+        let before = now_millis();
+
+        let response = service
+            .ping(tonic::Request::new(PingRequest { nonce: 42 }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.nonce, 42);
+        assert!(response.server_time >= before);
+        assert!(response.server_time <= now_millis());
+    }
+
+    #[tokio::test]
+    async fn create_post_requires_existing_author() {
+        let service = MyGrpcService::new();
+        let post = |user_id| {
+            tonic::Request::new(Post {
+                user_id,
+                title: "Hello".into(),
+                ..Default::default()
+            })
+        };
+
+        let status = service.create_post(post(42)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert_eq!(status.message(), "author does not exist");
+
+        assert!(service.create_post(post(1)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn watch_all_changes_streams_every_entity_type() {
+        let service = MyGrpcService::new();
+        let mut events = service
+            .watch_all_changes(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        service
+            .add_news(tonic::Request::new(News {
+                title: "Breaking".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        service
+            .delete_post(tonic::Request::new(PostRequest { id: 1 }))
+            .await
+            .unwrap();
+        service
+            .patch_user(tonic::Request::new(PatchUserRequest {
+                id: 1,
+                name: Some("Patched".into()),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            let event = events.next().await.unwrap().unwrap();
+            received.push((event.sequence, event.entity_type(), event.change_type()));
+        }
+        assert_eq!(
+            received,
+            vec![
+                (1, EntityType::News, ChangeType::Created),
+                (2, EntityType::Post, ChangeType::Deleted),
+                (3, EntityType::User, ChangeType::Updated),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn drain_is_forced_after_timeout() {
+        let in_flight = AtomicUsize::new(2);
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        // A server whose streams never close.
+        let server = std::future::pending::<()>();
+        shutdown_tx.send(()).unwrap();
+
+        let outcome = drain_with_timeout(
+            server,
+            async move {
+                let _ = shutdown_rx.await;
+            },
+            std::time::Duration::from_millis(10),
+            &in_flight,
+        )
+        .await;
+
+        assert_eq!(outcome, Drain::TimedOut { active: 2 });
+    }
+
+    #[tokio::test]
+    async fn drain_completes_when_server_finishes_in_time() {
+        let outcome = drain_with_timeout(
+            async { "done" },
+            std::future::pending(),
+            std::time::Duration::from_millis(10),
+            &AtomicUsize::new(0),
+        )
+        .await;
+
+        assert_eq!(outcome, Drain::Completed("done"));
+    }
+
+    #[tokio::test]
+    async fn list_posts_since_breaks_updated_at_ties_by_id() {
+        let service = MyGrpcService::new();
+        for post in service.posts.lock().unwrap().iter_mut() {
+            post.updated_at = SEED_TIMESTAMP;
+        }
+
+        let mut after = None;
+        let mut ids = Vec::new();
+        loop {
+            let page = service
+                .list_posts_since(tonic::Request::new(ListPostsSinceRequest {
+                    after: after.clone(),
+                    page_size: 1,
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            if page.posts.is_empty() {
+                break;
+            }
+            ids.extend(page.posts.iter().map(|p| p.id));
+            after = page.next_cursor;
+        }
+
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(
+            after,
+            Some(PostCursor {
+                updated_at: SEED_TIMESTAMP,
+                id: 2,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn convert_news_to_post_copies_content() {
+        let service = MyGrpcService::new();
+
+        let post = service
+            .convert_news_to_post(tonic::Request::new(ConvertNewsToPostRequest {
+                news_id: 2,
+                user_id: 1,
+                delete_source: true,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(post.user_id, 1);
+        assert_eq!(post.title, "Note 2");
+        assert_eq!(post.body, "Content 2");
+        assert!(service.posts.lock().unwrap().contains(&post));
+        assert!(!service.news.lock().unwrap().iter().any(|n| n.id == 2));
+    }
+
+    #[tokio::test]
+    async fn get_user_activity_lists_user_and_post_changes_newest_first() {
+        let service = MyGrpcService::new();
+        service
+            .patch_user(tonic::Request::new(PatchUserRequest {
+                id: 1,
+                name: Some("Patched".into()),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        let post = service
+            .create_post(tonic::Request::new(Post {
+                user_id: 1,
+                title: "Hello".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .post
+            .unwrap();
+        service
+            .add_news(tonic::Request::new(News {
+                title: "Anonymous".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+
+        let events = service
+            .get_user_activity(tonic::Request::new(UserActivityRequest {
+                user_id: 1,
+                limit: 10,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .events;
+
+        let summary: Vec<_> = events
+            .iter()
+            .map(|e| (e.entity_type(), e.id, e.change_type()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (EntityType::Post, post.id, ChangeType::Created),
+                (EntityType::User, 1, ChangeType::Updated),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn colliding_titles_get_suffixed_slugs() {
+        let service = MyGrpcService::new();
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let news = service
+                .add_news(tonic::Request::new(News {
+                    title: "Hello World".into(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            ids.push(news.id);
+        }
+
+        for (slug, id) in ["hello-world", "hello-world-2"].into_iter().zip(ids) {
+            let news = service
+                .get_news_by_slug(tonic::Request::new(NewsSlug { slug: slug.into() }))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(news.id, id);
+        }
+    }
+
+    #[tokio::test]
+    async fn list_posts_with_authors_embeds_each_author() {
+        let service = MyGrpcService::new();
+
+        let posts = service
+            .list_posts_with_authors(tonic::Request::new(PostFilter::default()))
+            .await
+            .unwrap()
+            .into_inner()
+            .posts;
+
+        assert_eq!(posts.len(), 2);
+        for entry in posts {
+            assert_eq!(entry.author.unwrap().id, 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn move_user_posts_reassigns_only_listed_posts() {
+        let service = MyGrpcService::new();
+        let target = service
+            .create_user(tonic::Request::new(User {
+                name: "Target".into(),
+                email: "target@example.com".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .user
+            .unwrap();
+
+        let results = service
+            .move_user_posts(tonic::Request::new(MoveUserPostsRequest {
+                post_ids: vec![1, 42],
+                target_user_id: target.id,
+                source_user_id: Some(1),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .results;
+
+        assert!(matches!(
+            &results[0].result,
+            Some(move_post_result::Result::Post(post)) if post.user_id == target.id
+        ));
+        assert!(matches!(
+            results[1].result,
+            Some(move_post_result::Result::Error(_))
+        ));
+        let posts = service.posts.lock().unwrap();
+        let owners: Vec<_> = posts.iter().map(|p| (p.id, p.user_id)).collect();
+        assert_eq!(owners, vec![(1, target.id), (2, 1)]);
+    }
+
+    #[tokio::test]
+    async fn get_news_statistics_summarizes_seeded_news() {
+        let service = MyGrpcService::new();
+
+        let stats = service
+            .get_news_statistics(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(stats.total, 5);
+        assert_eq!((stats.published, stats.draft, stats.deleted), (1, 4, 0));
+        assert_eq!(stats.average_body_length, "Content 1".len() as f64);
+        assert_eq!(stats.oldest_created_at, SEED_TIMESTAMP);
+        assert_eq!(stats.newest_created_at, SEED_TIMESTAMP);
+    }
+
+    #[tokio::test]
+    async fn delete_news_if_unpublished_only_deletes_drafts() {
+        let service = MyGrpcService::new();
+
+        let status = service
+            .delete_news_if_unpublished(tonic::Request::new(NewsId { id: 1 }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+        service
+            .delete_news_if_unpublished(tonic::Request::new(NewsId { id: 2 }))
+            .await
+            .unwrap();
+
+        let ids: Vec<_> = service.news.lock().unwrap().iter().map(|n| n.id).collect();
+        assert_eq!(ids, vec![1, 3, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn get_post_word_count_counts_words_and_chars() {
+        let service = MyGrpcService::new();
+        let post = service
+            .create_post(tonic::Request::new(Post {
+                user_id: 1,
+                title: "Counts".into(),
+                body: "  Héllo,\twide  wörld! ".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .post
+            .unwrap();
+
+        let counts = service
+            .get_post_word_count(tonic::Request::new(PostRequest { id: post.id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(counts.word_count, 3);
+        assert_eq!(counts.char_count, 22);
+
+        let status = service
+            .get_post_word_count(tonic::Request::new(PostRequest { id: 42 }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[test]
+    fn contended_store_lock_records_wait() {
+        let service = MyGrpcService::new();
+        let held = service.news.lock().unwrap();
+        let writer = {
+            let service = service.clone();
+            std::thread::spawn(move || service.lock_news().push(News::default()))
+        };
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        drop(held);
+        writer.join().unwrap();
+        // Uncontended acquisitions are not recorded.
+        drop(service.lock_news());
+
+        let wait = service
+            .metrics
+            .histogram(STORE_LOCK_WAIT_METRIC, &[("store", "news")])
+            .unwrap();
+        assert_eq!(wait.count, 1);
+        assert!(wait.max > 0.0);
+    }
+
+    fn transfer_request(
+        client_id: &str,
+        role: Role,
+        new_user_id: i32,
+    ) -> tonic::Request<TransferPostRequest> {
+        let mut request = tonic::Request::new(TransferPostRequest {
+            post_id: 1,
+            new_user_id,
+        });
+        request.extensions_mut().insert(ClientId(client_id.into()));
+        request.extensions_mut().insert(role);
+        request
+    }
+
+    #[tokio::test]
+    async fn transfer_post_allows_owner_and_denies_stranger() {
+        let service = MyGrpcService {
+            config: Arc::new(Config {
+                client_users: HashMap::from([("owner".into(), 1), ("stranger".into(), 7)]),
+                ..Config::default()
+            }),
+            ..MyGrpcService::new()
+        };
+        service.users.lock().unwrap().push(User {
+            id: 2,
+            ..Default::default()
+        });
+        let owner_of_post_1 = || service.posts.lock().unwrap()[0].user_id;
+
+        let status = service
+            .transfer_post(transfer_request("stranger", Role::Editor, 2))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        assert_eq!(owner_of_post_1(), 1);
+
+        let transferred = service
+            .transfer_post(transfer_request("owner", Role::Editor, 2))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(transferred.user_id, 2);
+        assert_eq!(owner_of_post_1(), 2);
+
+        // The previous owner lost the post with the transfer.
+        let status = service
+            .transfer_post(transfer_request("owner", Role::Editor, 1))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let transferred = service
+            .transfer_post(transfer_request("stranger", Role::Admin, 1))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(transferred.user_id, 1);
+        assert_eq!(owner_of_post_1(), 1);
+    }
+
+    #[tokio::test]
+    async fn check_reports_not_serving_when_unhealthy() {
+        let service = MyGrpcService::new();
+        service.health.set_ready(true);
+        assert_eq!(service.health.reported().await, ServingStatus::Serving);
+
+        assert!(service.health.set_healthy(false));
+
+        assert_eq!(service.health.reported().await, ServingStatus::NotServing);
+    }
+
+    #[tokio::test]
+    async fn list_news_by_author_filters_and_paginates() {
+        let service = MyGrpcService::new();
+        service.news.lock().unwrap().push(News {
+            id: 6,
+            title: "Other author".into(),
+            author_id: Some(2),
+            ..Default::default()
+        });
+        let list = |author_id, page_token: &str| {
+            service.list_news_by_author(tonic::Request::new(NewsByAuthorRequest {
+                author_id,
+                page_size: 1,
+                page_token: page_token.into(),
+            }))
+        };
+
+        let first = list(1, "").await.unwrap().into_inner();
+        assert_eq!(first.news.iter().map(|n| n.id).collect::<Vec<_>>(), [1]);
+        let second = list(1, &first.next_page_token).await.unwrap().into_inner();
+        assert_eq!(second.news.iter().map(|n| n.id).collect::<Vec<_>>(), [2]);
+        assert!(second.next_page_token.is_empty());
+
+        let other = list(2, "").await.unwrap().into_inner();
+        assert_eq!(other.news.iter().map(|n| n.id).collect::<Vec<_>>(), [6]);
+        assert!(list(99, "").await.unwrap().into_inner().news.is_empty());
+    }
+
+    #[tokio::test]
+    async fn create_post_with_client_id_depends_on_strict_mode() {
+        let post = || {
+            tonic::Request::new(Post {
+                id: 99,
+                user_id: 1,
+                title: "Hello".into(),
+                ..Default::default()
+            })
+        };
+
+        let lenient = MyGrpcService::new();
+        let created = lenient.create_post(post()).await.unwrap().into_inner();
+        assert_eq!(created.post.unwrap().id, 3);
+
+        let strict = MyGrpcService {
+            config: Arc::new(Config {
+                strict_mode: true,
+                ..Config::default()
+            }),
+            ..MyGrpcService::new()
+        };
+        let status = strict.create_post(post()).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(strict.posts.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn list_posts_paginates_within_user_filter() {
+        let service = MyGrpcService::new();
+        service.users.lock().unwrap().push(User {
+            id: 2,
+            ..Default::default()
+        });
+        for (id, user_id) in [(3, 2), (4, 1), (5, 2)] {
+            service.posts.lock().unwrap().push(Post {
+                id,
+                user_id,
+                ..Default::default()
+            });
+        }
+        let list = |user_id, page_token: String| {
+            service.list_posts(tonic::Request::new(PostFilter {
+                user_id,
+                page_size: 2,
+                page_token,
+            }))
+        };
+
+        let first = list(Some(1), String::new()).await.unwrap().into_inner();
+        assert_eq!(first.posts.iter().map(|p| p.id).collect::<Vec<_>>(), [1, 2]);
+        let second = list(Some(1), first.next_page_token.clone())
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(second.posts.iter().map(|p| p.id).collect::<Vec<_>>(), [4]);
+        assert!(second.next_page_token.is_empty());
+
+        let status = list(Some(2), first.next_page_token).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn get_user_with_posts_paginated_walks_pages() {
+        let service = MyGrpcService::new();
+        for id in 3..=5 {
+            service.posts.lock().unwrap().push(Post {
+                id,
+                user_id: 1,
+                ..Default::default()
+            });
+        }
+        let get = |page_token: String| {
+            service.get_user_with_posts_paginated(tonic::Request::new(UserPostsRequest {
+                id: 1,
+                page_size: 3,
+                page_token,
+            }))
+        };
+
+        let first = get(String::new()).await.unwrap().into_inner();
+        assert_eq!(first.user.unwrap().id, 1);
+        assert_eq!(
+            first.posts.iter().map(|p| p.id).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        let second = get(first.next_page_token).await.unwrap().into_inner();
+        assert_eq!(second.user.unwrap().id, 1);
+        assert_eq!(
+            second.posts.iter().map(|p| p.id).collect::<Vec<_>>(),
+            [4, 5]
+        );
+        assert!(second.next_page_token.is_empty());
+    }
+
+    #[tokio::test]
+    async fn get_news_by_id_or_slug_dispatches_on_key() {
+        let service = MyGrpcService::new();
+        let lookup = |key| service.get_news_by_id_or_slug(tonic::Request::new(NewsLookup { key }));
+
+        let by_id = lookup(Some(news_lookup::Key::Id(2))).await.unwrap();
+        assert_eq!(by_id.into_inner().slug, "note-2");
+
+        let by_slug = lookup(Some(news_lookup::Key::Slug("note-3".into())))
+            .await
+            .unwrap();
+        assert_eq!(by_slug.into_inner().id, 3);
+
+        let status = lookup(Some(news_lookup::Key::Slug("missing".into())))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        let status = lookup(None).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn create_post_is_recorded_in_audit_log() {
+        let service = MyGrpcService::new();
+        let context = middleware::audit::AuditContext {
+            client_id: "alice".into(),
+            method: "/posts.PostService/CreatePost".into(),
+        };
+
+        let created = middleware::audit::scope(
+            context,
+            service.create_post(tonic::Request::new(Post {
+                user_id: 1,
+                title: "Audited".into(),
+                ..Default::default()
+            })),
+        )
+        .await
+        .unwrap()
+        .into_inner()
+        .post
+        .unwrap();
+
+        let entries = service
+            .get_audit_log(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner()
+            .entries;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].method, "/posts.PostService/CreatePost");
+        assert_eq!(entries[0].client_id, "alice");
+        assert_eq!(entries[0].entity_type(), EntityType::Post);
+        assert_eq!(entries[0].entity_id, created.id);
+    }
+
+    #[tokio::test]
+    async fn list_posts_includes_meta_only_when_requested() {
+        let service = MyGrpcService::new();
+
+        let plain = service
+            .list_posts(tonic::Request::new(PostFilter::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(plain.meta, None);
+
+        let mut request = tonic::Request::new(PostFilter::default());
+        request
+            .metadata_mut()
+            .insert(INCLUDE_META_HEADER, "true".parse().unwrap());
+        request
+            .metadata_mut()
+            .insert("x-request-id", "req-42".parse().unwrap());
+        let meta = service
+            .list_posts(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .meta
+            .unwrap();
+        assert_eq!(meta.server_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(meta.request_id, "req-42");
+    }
+
+    #[tokio::test]
+    async fn publish_news_only_publishes_drafts() {
+        let service = MyGrpcService::new();
+        let mut events = service.news_events.subscribe();
+
+        let published = service
+            .publish_news(tonic::Request::new(NewsId { id: 2 }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(published.status(), NewsStatus::Published);
+        assert!(published.published_at > 0);
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.change_type(), ChangeType::Updated);
+        assert_eq!(event.news.unwrap().published_at, published.published_at);
+
+        let status = service
+            .publish_news(tonic::Request::new(NewsId { id: 2 }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn get_posts_by_ids_keeps_request_order_and_reports_missing() {
+        let service = MyGrpcService::new();
+
+        let response = service
+            .get_posts_by_ids(tonic::Request::new(PostIds {
+                ids: vec![2, 42, 1, 2, 7, 42],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let ids: Vec<_> = response.posts.iter().map(|p| p.id).collect();
+        assert_eq!(ids, [2, 1]);
+        assert_eq!(response.missing_ids, [42, 7]);
+    }
+
+    #[tokio::test]
+    async fn undo_last_change_removes_created_post() {
+        let service = MyGrpcService::new();
+        let post = service
+            .create_post(tonic::Request::new(Post {
+                user_id: 1,
+                title: "Oops".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .post
+            .unwrap();
+
+        let undone = service
+            .undo_last_change(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let [change] = &undone.changes[..] else {
+            panic!("expected one change, got {:?}", undone.changes);
+        };
+        assert_eq!(change.entity_type(), EntityType::Post);
+        assert_eq!(change.id, post.id);
+        assert_eq!(change.change_type(), ChangeType::Deleted);
+        assert!(!service
+            .posts
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|p| p.id == post.id));
+        let status = service
+            .undo_last_change(tonic::Request::new(()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn undo_last_change_restores_deleted_user() {
+        let service = MyGrpcService::new();
+        service
+            .delete_user(tonic::Request::new(UserRequest { id: 1 }))
+            .await
+            .unwrap();
+
+        service
+            .undo_last_change(tonic::Request::new(()))
+            .await
+            .unwrap();
+
+        assert!(service.users.lock().unwrap().iter().any(|u| u.id == 1));
+    }
+
+    #[tokio::test]
+    async fn get_users_by_email_domain_counts_domains() {
+        let service = MyGrpcService::new();
+        for email in ["a@Example.com", "b@example.com", "not-an-email"] {
+            let id = service.users.lock().unwrap().len() as i32 + 1;
+            service.users.lock().unwrap().push(User {
+                id,
+                email: email.into(),
+                ..Default::default()
+            });
+        }
+
+        let counts = service
+            .get_users_by_email_domain(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner()
+            .counts;
+
+        assert_eq!(counts["april.biz"], 1);
+        assert_eq!(counts["example.com"], 2);
+        assert_eq!(counts["unknown"], 1);
+    }
+
+    #[tokio::test]
+    async fn user_emails_are_stored_normalized() {
+        let service = MyGrpcService::new();
+
+        let user = service
+            .create_user(tonic::Request::new(User {
+                name: "Foo".into(),
+                email: "  Foo@Bar.com ".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .user
+            .unwrap();
+        assert_eq!(user.email, "foo@bar.com");
+
+        let patched = service
+            .patch_user(tonic::Request::new(PatchUserRequest {
+                id: user.id,
+                email: Some(" Baz@Example.COM".into()),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .user
+            .unwrap();
+        assert_eq!(patched.email, "baz@example.com");
+    }
+
+    #[test]
+    fn sync_deltas_reports_new_updated_and_deleted_items() {
+        let service = MyGrpcService::new();
+        service.news.lock().unwrap()[1].updated_at = SEED_TIMESTAMP + 10;
+        let held = HashMap::from([
+            (1, SEED_TIMESTAMP),
+            (2, SEED_TIMESTAMP),
+            (3, SEED_TIMESTAMP),
+            (4, SEED_TIMESTAMP),
+            (42, SEED_TIMESTAMP),
+        ]);
+
+        let deltas = sync_deltas(&service.news.lock().unwrap(), &held);
+
+        let summary: Vec<_> = deltas
+            .iter()
+            .map(|d| (d.change_type(), d.id, d.version))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (ChangeType::Updated, 2, SEED_TIMESTAMP + 10),
+                (ChangeType::Created, 5, SEED_TIMESTAMP),
+                (ChangeType::Deleted, 42, 0),
+            ]
+        );
+        assert!(deltas[2].news.is_none());
+    }
+
+    #[tokio::test]
+    async fn sync_news_streams_deltas_once_the_client_stream_closes() {
+        use grpc::news::news_service_client::NewsServiceClient;
+
+        let service = MyGrpcService::new();
+        service.news.lock().unwrap()[0].updated_at = SEED_TIMESTAMP + 10;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            TonicServer::builder()
+                .add_service(NewsServiceServer::new(service))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        let mut client = NewsServiceClient::connect(format!("http://{addr}"))
+            .await
+            .unwrap();
+        let (entries, rx) = tokio::sync::mpsc::channel(4);
+        let call = tokio::spawn(async move {
+            client
+                .sync_news(tokio_stream::wrappers::ReceiverStream::new(rx))
+                .await
+        });
+
+        for id in [1, 42] {
+            entries
+                .send(SyncEntry {
+                    id,
+                    version: SEED_TIMESTAMP,
+                })
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(
+            !call.is_finished(),
+            "responded before the client stream ended"
+        );
+
+        drop(entries);
+        let mut deltas = call.await.unwrap().unwrap().into_inner();
+        let mut summary = Vec::new();
+        while let Some(delta) = deltas.message().await.unwrap() {
+            summary.push((delta.change_type(), delta.id, delta.version));
+        }
+        assert_eq!(
+            summary,
+            [
+                (ChangeType::Updated, 1, SEED_TIMESTAMP + 10),
+                (ChangeType::Created, 2, SEED_TIMESTAMP),
+                (ChangeType::Created, 3, SEED_TIMESTAMP),
+                (ChangeType::Created, 4, SEED_TIMESTAMP),
+                (ChangeType::Created, 5, SEED_TIMESTAMP),
+                (ChangeType::Deleted, 42, 0),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn watch_news_batches_events_within_window() {
+        let service = MyGrpcService {
+            config: Arc::new(Config {
+                watch_batch: Some(std::time::Duration::from_millis(50)),
+                ..Config::default()
+            }),
+            ..MyGrpcService::new()
+        };
+        let mut events = service
+            .watch_news(tonic::Request::new(WatchNewsRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        for title in ["One", "Two", "Three"] {
+            service
+                .add_news(tonic::Request::new(News {
+                    title: title.into(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap();
+        }
+
+        let batch = events.next().await.unwrap().unwrap();
+        let sequences: Vec<_> = batch.batched.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, [1, 2, 3]);
+        assert_eq!(batch.news, None);
+    }
+
+    #[tokio::test]
+    async fn list_news_titles_returns_every_item() {
+        let service = MyGrpcService::new();
+
+        let titles = service
+            .list_news_titles(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner()
+            .titles;
+
+        let summary: Vec<_> = titles.iter().map(|t| (t.id, t.title.as_str())).collect();
+        assert_eq!(
+            summary,
+            [
+                (1, "Note 1"),
+                (2, "Note 2"),
+                (3, "Note 3"),
+                (4, "Note 4"),
+                (5, "Note 5")
+            ]
+        );
+        assert_eq!(titles[0].status(), NewsStatus::Published);
+        assert_eq!(titles[1].status(), NewsStatus::Draft);
+    }
+
+    #[tokio::test]
+    async fn get_post_revisions_attributes_each_update() {
+        let service = MyGrpcService::new();
+        let original = service.posts.lock().unwrap()[0].clone();
+        let update = |client_id: &str, post: Post| {
+            let mut request = tonic::Request::new(post);
+            request.extensions_mut().insert(ClientId(client_id.into()));
+            service.update_post(request)
+        };
+
+        update(
+            "alice",
+            Post {
+                title: "Retitled".into(),
+                ..original.clone()
+            },
+        )
+        .await
+        .unwrap();
+        update(
+            "bob",
+            Post {
+                title: "Retitled".into(),
+                body: "Rewritten".into(),
+                ..original.clone()
+            },
+        )
+        .await
+        .unwrap();
+
+        let revisions = service
+            .get_post_revisions(tonic::Request::new(PostRequest { id: original.id }))
+            .await
+            .unwrap()
+            .into_inner()
+            .revisions;
+        let summary: Vec<_> = revisions
+            .iter()
+            .map(|r| (r.version, r.client_id.as_str(), r.changed_fields.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (2, "bob", vec!["body".to_string()]),
+                (1, "alice", vec!["title".to_string()]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn dry_run_returns_without_binding() {
+        // Holding the port means a real bind would fail with "address in use".
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = MyGrpcService::from_config(Config {
+            dry_run: true,
+            ..Config::default()
+        });
+        let health = service.health.clone();
+
+        service.bind(addr).await.unwrap();
+        assert!(!health.is_ready());
+    }
+
+    #[tokio::test]
+    async fn get_news_feed_interleaves_requested_statuses() {
+        let service = MyGrpcService::new();
+        for news in service.news.lock().unwrap().iter_mut().take(3) {
+            news.set_status(NewsStatus::Published);
+        }
+        let slot = |status: NewsStatus, count| FeedSlot {
+            status: status as i32,
+            count,
+        };
+
+        let news = service
+            .get_news_feed(tonic::Request::new(FeedRequest {
+                slots: vec![
+                    slot(NewsStatus::Published, 3),
+                    slot(NewsStatus::Draft, 1),
+                    // Nothing is deleted, so this clamps to no items.
+                    slot(NewsStatus::Deleted, 2),
+                ],
             }))
-        } else {
-            Err(Status::not_found("User not found"))
+            .await
+            .unwrap()
+            .into_inner()
+            .news;
+
+        let statuses: Vec<_> = news.iter().map(|n| n.status()).collect();
+        assert_eq!(
+            statuses,
+            [
+                NewsStatus::Published,
+                NewsStatus::Draft,
+                NewsStatus::Published,
+                NewsStatus::Published
+            ]
+        );
+        // Equal timestamps fall back to the newest id first.
+        let ids: Vec<_> = news.iter().map(|n| n.id).collect();
+        assert_eq!(ids, [3, 5, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn create_user_reports_every_violation() {
+        let service = MyGrpcService::new();
+
+        let status = service
+            .create_user(tonic::Request::new(User {
+                name: " ".into(),
+                email: "not-an-email".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        let bad_request = status.get_details_bad_request().unwrap();
+        let fields: Vec<_> = bad_request
+            .field_violations
+            .iter()
+            .map(|v| v.field.as_str())
+            .collect();
+        assert_eq!(fields, ["name", "email"]);
+    }
+
+    #[tokio::test]
+    async fn create_user_applies_default_company() {
+        let service = MyGrpcService {
+            config: Arc::new(Config {
+                default_company_name: Some("Acme".into()),
+                ..Config::default()
+            }),
+            ..MyGrpcService::new()
+        };
+        let user = |name: &str, company: Option<Company>| User {
+            name: name.into(),
+            email: format!("{name}@example.com"),
+            company,
+            ..Default::default()
+        };
+
+        let defaulted = service
+            .create_user(tonic::Request::new(user("ann", None)))
+            .await
+            .unwrap()
+            .into_inner();
+        let explicit = service
+            .create_user(tonic::Request::new(user(
+                "bob",
+                Some(Company {
+                    name: "Initech".into(),
+                    ..Default::default()
+                }),
+            )))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let company_of = |response: UserResponse| response.user.unwrap().company.unwrap().name;
+        assert_eq!(company_of(defaulted), "Acme");
+        assert_eq!(company_of(explicit), "Initech");
+    }
+
+    #[tokio::test]
+    async fn get_news_created_histogram_buckets_by_utc_day() {
+        let service = MyGrpcService::new();
+        for news in service.news.lock().unwrap().iter_mut().skip(3) {
+            // Late on the following day, still before midnight UTC.
+            news.created_at = SEED_TIMESTAMP + 2 * MILLIS_PER_DAY - 1;
         }
+
+        let buckets = service
+            .get_news_created_histogram(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner()
+            .buckets;
+
+        let summary: Vec<_> = buckets.iter().map(|b| (b.date.as_str(), b.count)).collect();
+        assert_eq!(summary, [("2024-01-01", 3), ("2024-01-02", 2)]);
     }
-}
 
-static RESOURCE: Lazy<Resource> = Lazy::new(|| {
-    Resource::default().merge(&Resource::new(vec![
-        KeyValue::new(
-            opentelemetry_semantic_conventions::resource::SERVICE_NAME,
-            "rust-grpc",
-        ),
-        KeyValue::new(
-            opentelemetry_semantic_conventions::resource::SERVICE_VERSION,
-            "test",
-        ),
-    ]))
-});
+    #[tokio::test]
+    async fn reindex_news_repairs_corrupted_slugs_and_order() {
+        let service = MyGrpcService::new();
+        {
+            let mut news = service.news.lock().unwrap();
+            news[1].slug = "note-1".into();
+            news[2].slug.clear();
+            news.swap(3, 4);
+        }
 
-fn init_tracer() -> Result<()> {
-    global::set_text_map_propagator(TraceContextPropagator::new());
+        let report = service
+            .reindex_news(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner();
 
-    static TELEMETRY_URL: &str = "https://api.honeycomb.io:443";
-    let headers = HeaderMap::from_iter([(
-        HeaderName::from_static("x-honeycomb-team"),
-        HeaderValue::from_str(&std::env::var("HONEYCOMB_API_KEY")?)?,
-    )]);
+        assert_eq!(report.anomalies.len(), 3, "{:?}", report.anomalies);
+        assert_eq!(report.reslugged_ids, [2, 3]);
+        let news = service.news.lock().unwrap().clone();
+        let summary: Vec<_> = news.iter().map(|n| (n.id, n.slug.as_str())).collect();
+        assert_eq!(
+            summary,
+            [
+                (1, "note-1"),
+                (2, "note-2"),
+                (3, "note-3"),
+                (4, "note-4"),
+                (5, "note-5")
+            ]
+        );
 
-    let otlp_exporter = opentelemetry_otlp::new_exporter()
-        .tonic()
-        .with_endpoint(TELEMETRY_URL)
-        .with_metadata(MetadataMap::from_headers(headers));
+        let again = service
+            .reindex_news(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(again.anomalies.is_empty());
+        assert!(again.reslugged_ids.is_empty());
+    }
 
-    let provider = opentelemetry_otlp::new_pipeline()
-        .tracing()
-        .with_exporter(otlp_exporter)
-        .with_trace_config(opentelemetry_sdk::trace::config().with_resource(RESOURCE.clone()))
-        .install_batch(runtime::Tokio)?
-        .provider()
-        .ok_or(TraceError::Other(
-            anyhow!("Failed to instantiate OTLP provider").into(),
-        ))?;
+    #[tokio::test]
+    async fn zstd_client_round_trips_list_call() {
+        use grpc::news::news_service_client::NewsServiceClient;
+        use tonic::codec::CompressionEncoding;
 
-    let tracer = provider.tracer("tracing");
-    let trace_layer = tracing_opentelemetry::layer()
-        .with_location(false)
-        .with_threads(false)
-        .with_tracer(tracer);
+        let service = MyGrpcService::from_config(Config {
+            compression_algos: vec![CompressionEncoding::Gzip, CompressionEncoding::Zstd],
+            ..Config::default()
+        });
+        let algos = service.config.compression_algos.clone();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            TonicServer::builder()
+                .add_service(with_compression!(NewsServiceServer::new(service), algos))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
 
-    let subscriber = tracing_subscriber::registry().with(trace_layer);
+        let mut client = NewsServiceClient::connect(format!("http://{addr}"))
+            .await
+            .unwrap()
+            .send_compressed(CompressionEncoding::Zstd)
+            .accept_compressed(CompressionEncoding::Zstd);
+        let response = client.get_all_news(()).await.unwrap();
 
-    tracing::subscriber::set_global_default(subscriber)?;
+        assert_eq!(
+            response
+                .metadata()
+                .get("grpc-encoding")
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "zstd"
+        );
+        assert_eq!(response.into_inner().news.len(), 5);
+    }
 
-    global::set_tracer_provider(provider);
+    #[tokio::test]
+    async fn get_orphaned_posts_reports_posts_without_users() {
+        let service = MyGrpcService {
+            config: Arc::new(Config {
+                skip_author_check: true,
+                ..Config::default()
+            }),
+            ..MyGrpcService::new()
+        };
+        let orphan = service
+            .create_post(tonic::Request::new(Post {
+                user_id: 99,
+                title: "Nobody's".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .post
+            .unwrap();
 
-    Ok(())
-}
+        let posts = service
+            .get_orphaned_posts(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner()
+            .posts;
 
-#[shuttle_runtime::main]
-async fn shuttle_main() -> Result<impl Service, shuttle_runtime::Error> {
-    if std::env::var("HONEYCOMB_API_KEY").is_ok() {
-        init_tracer()?;
+        assert_eq!(posts, [orphan]);
     }
 
-    let grpc_service = MyGrpcService::new();
+    #[tokio::test]
+    async fn get_all_news_is_cached_until_news_changes() {
+        let service = MyGrpcService::from_config(Config {
+            read_cache_ttl: Some(std::time::Duration::from_secs(60)),
+            ..Config::default()
+        });
+        let lookups = |result| {
+            service
+                .metrics
+                .histogram(
+                    READ_CACHE_METRIC,
+                    &[
+                        ("method", "/news.NewsService/GetAllNews"),
+                        ("result", result),
+                    ],
+                )
+                .map_or(0, |h| h.count)
+        };
+        let read = || service.get_all_news(tonic::Request::new(()));
 
-    Ok(grpc_service)
-}
+        let first = read().await.unwrap().into_inner();
+        let second = read().await.unwrap().into_inner();
+        assert_eq!(first, second);
+        assert_eq!((lookups("miss"), lookups("hit")), (1, 1));
 
-#[async_trait::async_trait]
-impl Service for MyGrpcService {
-    async fn bind(mut self, addr: std::net::SocketAddr) -> Result<(), shuttle_runtime::Error> {
-        let service = tonic_reflection::server::Builder::configure()
-            .register_encoded_file_descriptor_set(grpc::FILE_DESCRIPTOR_SET)
-            .build()
+        service
+            .add_news(tonic::Request::new(News {
+                title: "Fresh".into(),
+                ..Default::default()
+            }))
+            .await
             .unwrap();
+        let third = read().await.unwrap().into_inner();
+        assert_eq!(third.news.len(), first.news.len() + 1);
+        assert_eq!(lookups("miss"), 2);
+    }
 
-        println!("NewsService server listening on {}", addr);
+    #[tokio::test]
+    async fn watch_news_forwards_only_matching_statuses() {
+        let service = MyGrpcService::new();
+        let mut events = service
+            .watch_news(tonic::Request::new(WatchNewsRequest {
+                statuses: vec![NewsStatus::Published as i32],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
 
-        let tonic_service = TonicServer::builder()
-            .layer(server::OtelGrpcLayer::default())
-            .add_service(NewsServiceServer::new(self.clone()))
-            .add_service(PostServiceServer::new(self.clone()))
-            .add_service(UserServiceServer::new(self))
-            .add_service(service)
-            .into_service();
-        let make_svc = Shared::new(tonic_service);
+        let draft = service.news.lock().unwrap()[1].clone();
+        service
+            .edit_news(tonic::Request::new(News {
+                title: "Still a draft".into(),
+                ..draft
+            }))
+            .await
+            .unwrap();
+        service
+            .publish_news(tonic::Request::new(NewsId { id: 3 }))
+            .await
+            .unwrap();
 
-        let server = hyper::Server::bind(&addr).serve(make_svc);
-        server
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.news.unwrap().id, 3);
+        // The draft edit took sequence 1.
+        assert_eq!(event.sequence, 2);
+    }
+
+    #[tokio::test]
+    async fn get_post_scoped_requires_matching_owner() {
+        let service = MyGrpcService::new();
+        let lookup = |user_id, id| {
+            service.get_post_scoped(tonic::Request::new(ScopedPostRequest { user_id, id }))
+        };
+
+        let post = lookup(1, 2).await.unwrap().into_inner();
+        assert_eq!((post.id, post.user_id), (2, 1));
+
+        let wrong_owner = lookup(2, 2).await.unwrap_err();
+        let missing = lookup(2, 99).await.unwrap_err();
+        assert_eq!(wrong_owner.code(), tonic::Code::NotFound);
+        assert_eq!(wrong_owner.message(), missing.message());
+    }
+
+    #[tokio::test]
+    async fn watchers_are_capped_until_a_stream_closes() {
+        let service = MyGrpcService::from_config(Config {
+            max_watchers: Some(1),
+            ..Config::default()
+        });
+
+        let news = service
+            .watch_news(tonic::Request::new(WatchNewsRequest::default()))
             .await
-            .map_err(|e| shuttle_runtime::Error::Custom(anyhow::anyhow!(e)))?;
+            .unwrap();
+        let rejected = service
+            .watch_all_changes(tonic::Request::new(()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(rejected.code(), tonic::Code::ResourceExhausted);
 
-        Ok(())
+        drop(news);
+        assert!(service
+            .watch_all_changes(tonic::Request::new(()))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn validate_news_lists_violations_without_storing() {
+        let service = MyGrpcService::new();
+        let validate = |title: &str| {
+            service.validate_news(tonic::Request::new(News {
+                title: title.into(),
+                ..Default::default()
+            }))
+        };
+
+        let valid = validate("Fine").await.unwrap().into_inner();
+        assert!(valid.violations.is_empty());
+
+        let invalid = validate("  ").await.unwrap().into_inner();
+        assert_eq!(
+            invalid.violations,
+            [Violation {
+                field: "title".into(),
+                description: "title must not be empty".into(),
+            }]
+        );
+        assert_eq!(service.news.lock().unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn validate_news_reports_language_and_body_limit_like_add_news() {
+        let service = MyGrpcService::from_config(Config {
+            max_create_body: Some(4),
+            ..Config::default()
+        });
+        let request = || {
+            let mut request = tonic::Request::new(News {
+                title: "Sized".into(),
+                body: "12345".into(),
+                ..Default::default()
+            });
+            request
+                .metadata_mut()
+                .insert(schema_version::SCHEMA_VERSION_HEADER, "2".parse().unwrap());
+            request
+        };
+
+        let result = service.validate_news(request()).await.unwrap().into_inner();
+        let fields: Vec<_> = result.violations.iter().map(|v| v.field.as_str()).collect();
+        assert_eq!(fields, ["language", "body"]);
+
+        let err = service.add_news(request()).await.unwrap_err();
+        let details = err.get_details_bad_request().unwrap();
+        let fields: Vec<_> = details
+            .field_violations
+            .iter()
+            .map(|v| v.field.as_str())
+            .collect();
+        assert_eq!(fields, ["language", "body"]);
+    }
+
+    #[tokio::test]
+    async fn get_user_login_hint_masks_contact_details() {
+        let service = MyGrpcService::new();
+
+        let hint = service
+            .get_user_login_hint(tonic::Request::new(UserRequest { id: 1 }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(hint.masked_email, "s***@april.biz");
+        assert_eq!(hint.masked_phone, "*-***-***-8031");
+    }
+
+    #[tokio::test]
+    async fn get_user_login_hint_is_rate_limited() {
+        let service = MyGrpcService::from_config(Config {
+            login_hint_rate_limit: 1,
+            ..Config::default()
+        });
+        let hint = || service.get_user_login_hint(tonic::Request::new(UserRequest { id: 1 }));
+
+        hint().await.unwrap();
+        let status = hint().await.unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    }
+
+    #[tokio::test]
+    async fn seed_count_replaces_fixtures_with_generated_records() {
+        let service = MyGrpcService::from_config(Config {
+            seed_count: Some(100),
+            ..Config::default()
+        });
+
+        let users = service.users.lock().unwrap().clone();
+        assert_eq!(users.len(), 100);
+        let ids: HashSet<_> = users.iter().map(|u| u.id).collect();
+        let emails: HashSet<_> = users.iter().map(|u| u.email.as_str()).collect();
+        assert_eq!((ids.len(), emails.len()), (100, 100));
+        assert_eq!(service.posts.lock().unwrap().len(), 100);
+        assert_eq!(service.news.lock().unwrap().len(), 100);
+    }
+
+    #[tokio::test]
+    async fn diff_news_reports_title_only_change() {
+        let service = MyGrpcService::new();
+        let current = service.news.lock().unwrap()[0].clone();
+
+        let changes = service
+            .diff_news(tonic::Request::new(DiffNewsRequest {
+                id: current.id,
+                candidate: Some(News {
+                    title: "Retitled".into(),
+                    ..current.clone()
+                }),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .changes;
+
+        assert_eq!(
+            changes,
+            [FieldChange {
+                field: "title".into(),
+                old_value: "Note 1".into(),
+                new_value: "Retitled".into(),
+            }]
+        );
+        assert_eq!(service.news.lock().unwrap()[0], current);
+    }
+
+    #[tokio::test]
+    async fn shutdown_reports_not_serving_before_draining() {
+        let service = MyGrpcService::new();
+        service.health.set_ready(true);
+        let drained = Arc::new(AtomicBool::new(false));
+        let shutdown = tokio::spawn(shutdown_sequence(
+            async {},
+            service.health.clone(),
+            std::time::Duration::from_millis(200),
+            {
+                let drained = drained.clone();
+                move || drained.store(true, Ordering::SeqCst)
+            },
+        ));
+
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(service.health.reported().await, ServingStatus::NotServing);
+        assert!(!drained.load(Ordering::SeqCst));
+
+        shutdown.await.unwrap();
+        assert!(drained.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn get_duplicate_post_titles_groups_case_variants() {
+        let service = MyGrpcService::new();
+        let mut ids = Vec::new();
+        for title in ["Hello World", "  hello WORLD "] {
+            let post = service
+                .create_post(tonic::Request::new(Post {
+                    user_id: 1,
+                    title: title.into(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner()
+                .post
+                .unwrap();
+            ids.push(post.id);
+        }
+
+        let groups = service
+            .get_duplicate_post_titles(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner()
+            .groups;
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].normalized_title, "hello world");
+        let grouped: Vec<_> = groups[0].posts.iter().map(|p| p.id).collect();
+        assert_eq!(grouped, ids);
+    }
+
+    #[test]
+    fn snapshot_round_trips_the_store_in_both_formats() {
+        for format in [SnapshotFormat::Json, SnapshotFormat::Proto] {
+            let dir = tempfile::tempdir().unwrap();
+            let config = Config {
+                snapshot_path: Some(dir.path().join("store.snapshot")),
+                snapshot_format: format,
+                ..Config::default()
+            };
+            let service = MyGrpcService::from_config(config.clone());
+            service.news.lock().unwrap()[0].title = "Edited".into();
+            service.posts.lock().unwrap().pop();
+            service.save_snapshot().unwrap();
+
+            let restored = MyGrpcService::from_config(config);
+            restored.restore_snapshot().unwrap();
+
+            assert_eq!(restored.snapshot(), service.snapshot(), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn missing_snapshot_keeps_seed_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = MyGrpcService::from_config(Config {
+            snapshot_path: Some(dir.path().join("absent")),
+            ..Config::default()
+        });
+
+        service.restore_snapshot().unwrap();
+
+        assert_eq!(service.news.lock().unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn list_news_older_than_returns_oldest_first() {
+        let service = MyGrpcService::new();
+        for (news, created_at) in service
+            .news
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .zip([500, 100, 300, 900, 200])
+        {
+            news.created_at = created_at;
+        }
+
+        let list = |status: Option<NewsStatus>| {
+            let service = &service;
+            async move {
+                service
+                    .list_news_older_than(tonic::Request::new(OlderThanRequest {
+                        before: 500,
+                        status: status.map(Into::into),
+                    }))
+                    .await
+                    .unwrap()
+                    .into_inner()
+                    .news
+                    .iter()
+                    .map(|n| n.id)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(list(None).await, [2, 5, 3]);
+        assert_eq!(list(Some(NewsStatus::Published)).await, Vec::<i32>::new());
+        assert_eq!(list(Some(NewsStatus::Draft)).await, [2, 5, 3]);
+    }
+
+    #[tokio::test]
+    async fn purge_removes_only_expired_soft_deleted_news() {
+        let day = std::time::Duration::from_secs(24 * 60 * 60);
+        let service = MyGrpcService::from_config(Config {
+            soft_delete_retention: Some(30 * day),
+            ..Config::default()
+        });
+        let now = SEED_TIMESTAMP + 100 * MILLIS_PER_DAY;
+        {
+            let mut news = service.news.lock().unwrap();
+            news[1].set_status(NewsStatus::Deleted);
+            news[1].updated_at = now - 31 * MILLIS_PER_DAY;
+            news[2].set_status(NewsStatus::Deleted);
+            news[2].updated_at = now - 29 * MILLIS_PER_DAY;
+            news[3].updated_at = now - 31 * MILLIS_PER_DAY;
+        }
+        let mut events = service.news_events.subscribe();
+
+        assert_eq!(service.purge_soft_deleted(now), [2]);
+
+        let ids: Vec<i32> = service.news.lock().unwrap().iter().map(|n| n.id).collect();
+        assert_eq!(ids, [1, 3, 4, 5]);
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.change_type(), ChangeType::Deleted);
+        assert_eq!(event.news.unwrap().id, 2);
+    }
+
+    #[tokio::test]
+    async fn list_methods_names_news_rpcs() {
+        let service = MyGrpcService::new();
+
+        let services = service
+            .list_methods(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner()
+            .services;
+
+        let news = services
+            .iter()
+            .find(|s| s.service == "news.NewsService")
+            .unwrap();
+        assert!(news.methods.iter().any(|m| m == "GetNews"));
+        assert!(news.methods.iter().any(|m| m == "AddNews"));
+        assert!(services
+            .iter()
+            .any(|s| s.service == "diagnostics.DiagnosticsService"
+                && s.methods.iter().any(|m| m == "ListMethods")));
+    }
+
+    #[tokio::test]
+    async fn add_news_defaults_language_for_v1_clients() {
+        let service = MyGrpcService::new();
+        let request = |version: &'static str| {
+            let mut request = tonic::Request::new(News {
+                title: format!("From v{version}"),
+                ..Default::default()
+            });
+            request.metadata_mut().insert(
+                schema_version::SCHEMA_VERSION_HEADER,
+                version.parse().unwrap(),
+            );
+            request
+        };
+
+        let news = service.add_news(request("1")).await.unwrap().into_inner();
+        assert_eq!(news.language, schema_version::DEFAULT_NEWS_LANGUAGE);
+
+        let err = service.add_news(request("2")).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    async fn search_news_ids(
+        service: &MyGrpcService,
+        keywords: &[&str],
+        match_all: bool,
+    ) -> Vec<i32> {
+        service
+            .search_news(tonic::Request::new(NewsSearchQuery {
+                keywords: keywords.iter().map(|k| k.to_string()).collect(),
+                match_all,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .news
+            .iter()
+            .map(|n| n.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn search_news_matches_all_keywords() {
+        let service = MyGrpcService::new();
+        {
+            let mut news = service.news.lock().unwrap();
+            news[1].body = "Rust and gRPC".into();
+            news[2].title = "rust only".into();
+        }
+
+        assert_eq!(
+            search_news_ids(&service, &["RUST", "grpc"], true).await,
+            [2]
+        );
+    }
+
+    #[tokio::test]
+    async fn search_news_matches_any_keyword() {
+        let service = MyGrpcService::new();
+        {
+            let mut news = service.news.lock().unwrap();
+            news[1].body = "Rust and gRPC".into();
+            news[2].title = "rust only".into();
+            news[4].body = "About GRPC".into();
+        }
+
+        assert_eq!(
+            search_news_ids(&service, &["rust", "grpc", " "], false).await,
+            [2, 3, 5]
+        );
+        let err = service
+            .search_news(tonic::Request::new(NewsSearchQuery {
+                keywords: vec![" ".into()],
+                match_all: false,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn create_and_update_body_limits_apply_independently() {
+        let service = MyGrpcService::from_config(Config {
+            max_create_body: Some(4),
+            max_update_body: Some(8),
+            ..Config::default()
+        });
+        let news = |id: i32, body: &str| {
+            tonic::Request::new(News {
+                id,
+                title: "Sized".into(),
+                body: body.into(),
+                ..Default::default()
+            })
+        };
+
+        let err = service.add_news(news(0, "12345")).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        service.add_news(news(0, "1234")).await.unwrap();
+
+        service.edit_news(news(1, "12345678")).await.unwrap();
+        let err = service.edit_news(news(1, "123456789")).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        let post = |id: i32, body: &str| {
+            tonic::Request::new(Post {
+                id,
+                user_id: 1,
+                title: "Sized".into(),
+                body: body.into(),
+                ..Default::default()
+            })
+        };
+        let err = service.create_post(post(0, "12345")).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        service.update_post(post(1, "12345")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn get_related_news_ranks_by_shared_keywords() {
+        let service = MyGrpcService::new();
+        {
+            let mut news = service.news.lock().unwrap();
+            news[0].title = "Rust async runtime".into();
+            news[0].body = "Tokio schedules tasks".into();
+            for (item, title) in news.iter_mut().skip(1).zip([
+                "Async Rust with Tokio",
+                "Rust releases",
+                "Gardening tips",
+                "Async Rust with Tokio (draft)",
+            ]) {
+                item.title = title.into();
+                item.body.clear();
+            }
+            for item in news.iter_mut().take(4) {
+                item.set_status(NewsStatus::Published);
+            }
+        }
+
+        let related = service
+            .get_related_news(tonic::Request::new(RelatedNewsRequest { id: 1, limit: 0 }))
+            .await
+            .unwrap()
+            .into_inner()
+            .news;
+
+        let ids: Vec<i32> = related.iter().map(|n| n.id).collect();
+        assert_eq!(ids, [2, 3]);
+    }
+
+    #[tokio::test]
+    async fn undo_last_change_restores_every_post_of_delete_posts_by_user() {
+        let service = MyGrpcService::new();
+        let before = service.posts.lock().unwrap().clone();
+        service
+            .delete_posts_by_user(tonic::Request::new(DeletePostsByUserRequest { user_id: 1 }))
+            .await
+            .unwrap();
+
+        let undone = service
+            .undo_last_change(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(undone.changes.len(), 2);
+        assert!(undone
+            .changes
+            .iter()
+            .all(|c| c.change_type() == ChangeType::Created));
+        assert_eq!(*service.posts.lock().unwrap(), before);
+        let status = service
+            .undo_last_change(tonic::Request::new(()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn undo_last_change_rolls_back_news_history() {
+        let service = MyGrpcService::new();
+        service
+            .edit_news(tonic::Request::new(News {
+                id: 1,
+                title: "Edited".into(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        assert!(service.news_history.lock().unwrap().contains_key(&1));
+
+        service
+            .undo_last_change(tonic::Request::new(()))
+            .await
+            .unwrap();
+
+        assert!(!service.news_history.lock().unwrap().contains_key(&1));
+        assert_eq!(service.news.lock().unwrap()[0].title, "Note 1");
     }
 }
@@ -0,0 +1,79 @@
+//! In-process metrics registry used by the middleware and store instrumentation.
+
+use std::{collections::HashMap, sync::Mutex};
+
+pub type Labels = Vec<(&'static str, String)>;
+
+/// Milliseconds spent waiting for a contended store lock.
+pub const STORE_LOCK_WAIT_METRIC: &str = "store_lock_wait_ms";
+
+/// One observation per cached read, labeled by method and whether it was a `hit` or a `miss`
+/// served from the store.
+pub const READ_CACHE_METRIC: &str = "read_cache_lookups";
+
+/// Aggregated view of the values recorded into a histogram.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct HistogramSummary {
+    pub count: u64,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl HistogramSummary {
+    fn observe(&mut self, value: f64) {
+        if self.count == 0 || value < self.min {
+            self.min = value;
+        }
+        if self.count == 0 || value > self.max {
+            self.max = value;
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    histograms: Mutex<HashMap<(&'static str, Labels), HistogramSummary>>,
+}
+
+impl Metrics {
+    /// Records `value` into the histogram `name` with the given labels.
+    pub fn record(&self, name: &'static str, labels: &[(&'static str, &str)], value: f64) {
+        let mut histograms = self.histograms.lock().unwrap();
+        histograms
+            .entry((name, to_owned_labels(labels)))
+            .or_default()
+            .observe(value);
+    }
+
+    /// Returns every histogram recorded so far, ordered by name and labels.
+    #[cfg(test)]
+    pub fn histograms(&self) -> Vec<(&'static str, Labels, HistogramSummary)> {
+        let histograms = self.histograms.lock().unwrap();
+        let mut snapshot: Vec<_> = histograms
+            .iter()
+            .map(|((name, labels), summary)| (*name, labels.clone(), *summary))
+            .collect();
+        snapshot.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+        snapshot
+    }
+
+    #[cfg(test)]
+    pub fn histogram(
+        &self,
+        name: &'static str,
+        labels: &[(&'static str, &str)],
+    ) -> Option<HistogramSummary> {
+        let histograms = self.histograms.lock().unwrap();
+        histograms.get(&(name, to_owned_labels(labels))).copied()
+    }
+}
+
+fn to_owned_labels(labels: &[(&'static str, &str)]) -> Labels {
+    labels
+        .iter()
+        .map(|(key, value)| (*key, value.to_string()))
+        .collect()
+}
@@ -0,0 +1,209 @@
+//! Appends one JSON line per request to an access log file.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use hyper::{Request, Response};
+use serde::Serialize;
+use tonic::body::BoxBody;
+use tower::{Layer, Service};
+
+use super::{BoxFuture, ConnectionInfo};
+
+/// Only request metadata is logged, never message fields, so user data such as emails and
+/// phone numbers cannot reach the file whatever `LOG_REDACT` is set to.
+#[derive(Debug, Serialize)]
+struct Entry<'a> {
+    timestamp: i64,
+    method: &'a str,
+    /// gRPC status code from the response head. Errors sent in trailers after a successful
+    /// head, such as a stream failing midway, are logged as `0`.
+    status: i32,
+    duration_ms: f64,
+    peer: Option<SocketAddr>,
+}
+
+/// An append-only access log file shared by every connection.
+#[derive(Debug)]
+pub struct AccessLog {
+    file: Mutex<File>,
+}
+
+impl AccessLog {
+    /// Opens `path` for appending, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    fn write(&self, entry: &Entry) {
+        let mut line = match serde_json::to_vec(entry) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to encode access log entry");
+                return;
+            }
+        };
+        line.push(b'\n');
+        // A single `write_all` of the whole line keeps entries intact in append mode. Failures
+        // are logged rather than failing the request.
+        if let Err(e) = self.file.lock().unwrap().write_all(&line) {
+            tracing::warn!(error = %e, "failed to write access log entry");
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AccessLogLayer {
+    log: Option<Arc<AccessLog>>,
+}
+
+impl AccessLogLayer {
+    /// Logging is disabled when `log` is `None`.
+    pub fn new(log: Option<Arc<AccessLog>>) -> Self {
+        Self { log }
+    }
+}
+
+impl<S> Layer<S> for AccessLogLayer {
+    type Service = AccessLogService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AccessLogService {
+            inner,
+            log: self.log.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AccessLogService<S> {
+    inner: S,
+    log: Option<Arc<AccessLog>>,
+}
+
+impl<S, B> Service<Request<B>> for AccessLogService<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let Some(log) = self.log.clone() else {
+            return Box::pin(async move { inner.call(request).await });
+        };
+
+        let method = request.uri().path().to_string();
+        let peer = request
+            .extensions()
+            .get::<ConnectionInfo>()
+            .and_then(|info| info.peer);
+        Box::pin(async move {
+            let started_at = Instant::now();
+            let response = inner.call(request).await?;
+            let status = response
+                .headers()
+                .get("grpc-status")
+                .map_or(tonic::Code::Ok, |code| {
+                    tonic::Code::from_bytes(code.as_bytes())
+                });
+            log.write(&Entry {
+                timestamp: crate::now_millis(),
+                method: &method,
+                status: status as i32,
+                duration_ms: started_at.elapsed().as_secs_f64() * 1000.0,
+                peer,
+            });
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn not_found(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        Ok(tonic::Status::not_found("missing").to_http())
+    }
+
+    #[tokio::test]
+    async fn appends_one_json_line_per_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("access.log");
+        std::fs::write(&path, "existing\n").unwrap();
+        let log = Arc::new(AccessLog::open(&path).unwrap());
+        let service = AccessLogLayer::new(Some(log)).layer(tower::service_fn(not_found));
+
+        let mut request = Request::builder()
+            .uri("/posts.PostService/GetPost")
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(ConnectionInfo {
+            tls: false,
+            peer: Some("127.0.0.1:5000".parse().unwrap()),
+        });
+        service.oneshot(request).await.unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let entry: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(entry["method"], "/posts.PostService/GetPost");
+        assert_eq!(entry["status"], tonic::Code::NotFound as i32);
+        assert_eq!(entry["peer"], "127.0.0.1:5000");
+        assert!(entry["duration_ms"].as_f64().unwrap() >= 0.0);
+    }
+
+    #[tokio::test]
+    async fn entries_never_contain_request_fields() {
+        use prost::Message;
+
+        use crate::grpc::users::User;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("access.log");
+        let log = Arc::new(AccessLog::open(&path).unwrap());
+        let service = AccessLogLayer::new(Some(log)).layer(tower::service_fn(not_found));
+        let user = User {
+            email: "sincere@april.biz".into(),
+            phone: "1-770-736-8031".into(),
+            ..Default::default()
+        };
+
+        let request = Request::builder()
+            .uri("/users.UserService/CreateUser")
+            .body(Body::from(user.encode_to_vec()))
+            .unwrap();
+        service.oneshot(request).await.unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("/users.UserService/CreateUser"));
+        assert!(!contents.contains("april.biz"));
+        assert!(!contents.contains("736-8031"));
+    }
+}
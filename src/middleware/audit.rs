@@ -0,0 +1,137 @@
+//! Attributes mutations to the calling client for the audit log.
+//!
+//! For mutating RPCs the layer makes an [`AuditContext`] available to the handler through a
+//! task-local, which `publish_change` reads to write one audit entry per changed entity. Since
+//! task-locals do not cross `tokio::spawn`, this layer must sit inside `CatchPanicLayer`.
+
+use std::{
+    future::Future,
+    task::{Context, Poll},
+};
+
+use hyper::{Request, Response};
+use tonic::body::BoxBody;
+use tower::{Layer, Service};
+
+use super::{is_mutating, BoxFuture, ClientId};
+
+tokio::task_local! {
+    static CONTEXT: AuditContext;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditContext {
+    /// Empty when authentication is disabled.
+    pub client_id: String,
+    /// Full gRPC path, e.g. `/posts.PostService/CreatePost`.
+    pub method: String,
+}
+
+/// Runs `future` with `context` as the current audit context.
+pub async fn scope<F: Future>(context: AuditContext, future: F) -> F::Output {
+    CONTEXT.scope(context, future).await
+}
+
+/// Audit context of the mutating RPC being handled, if any.
+pub fn current() -> Option<AuditContext> {
+    CONTEXT.try_with(Clone::clone).ok()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuditLayer;
+
+impl<S> Layer<S> for AuditLayer {
+    type Service = Audit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Audit { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Audit<S> {
+    inner: S,
+}
+
+impl<S, B> Service<Request<B>> for Audit<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let method = request.uri().path();
+        if !is_mutating(method) {
+            return Box::pin(async move { inner.call(request).await });
+        }
+
+        let context = AuditContext {
+            client_id: request
+                .extensions()
+                .get::<ClientId>()
+                .map(|ClientId(id)| id.clone())
+                .unwrap_or_default(),
+            method: method.to_string(),
+        };
+        Box::pin(scope(context, inner.call(request)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn context_header(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        let mut response = Response::new(tonic::body::empty_body());
+        if let Some(context) = current() {
+            let value = format!("{} {}", context.client_id, context.method);
+            response
+                .headers_mut()
+                .insert("x-audit", value.parse().unwrap());
+        }
+        Ok(response)
+    }
+
+    async fn audit_header(path: &str) -> Option<String> {
+        let service = AuditLayer.layer(tower::service_fn(context_header));
+        let mut request = Request::builder().uri(path).body(Body::empty()).unwrap();
+        request.extensions_mut().insert(ClientId("alice".into()));
+
+        let response = service.oneshot(request).await.unwrap();
+
+        response
+            .headers()
+            .get("x-audit")
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn exposes_context_to_mutating_handlers() {
+        assert_eq!(
+            audit_header("/posts.PostService/CreatePost")
+                .await
+                .as_deref(),
+            Some("alice /posts.PostService/CreatePost")
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_read_only_methods() {
+        assert_eq!(audit_header("/posts.PostService/GetPost").await, None);
+    }
+}
@@ -0,0 +1,334 @@
+//! API-key authentication.
+//!
+//! Clients send `authorization: Bearer <key>`. The key is mapped to a client id which is
+//! attached to the request extensions and recorded on the request span as `enduser.id`.
+//! The raw key is never logged.
+//!
+//! Each key also has a [`Role`] gating which RPCs it may call. With authentication disabled
+//! nobody can hold the admin role, so admin-only RPCs are denied to every caller.
+
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use hyper::{Request, Response};
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use super::{is_admin_only, is_mutating, is_public, BoxFuture};
+
+const ENDUSER_ID_ATTRIBUTE: &str = "enduser.id";
+
+/// Id of the authenticated caller, available to handlers through the request extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientId(pub String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// May only call read RPCs.
+    Reader,
+    /// May call read and mutating RPCs, except admin-only ones.
+    Editor,
+    /// May call every RPC.
+    Admin,
+}
+
+impl Role {
+    pub fn allows(self, path: &str) -> bool {
+        match self {
+            Role::Admin => true,
+            Role::Editor => !is_admin_only(path),
+            Role::Reader => !is_admin_only(path) && !is_mutating(path),
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reader" => Ok(Role::Reader),
+            "editor" => Ok(Role::Editor),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("unknown role {other:?}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthLayer {
+    /// API key to client id. Authentication is disabled when empty.
+    api_keys: Arc<HashMap<String, String>>,
+    /// API key to role. Keys without an entry are readers.
+    roles: Arc<HashMap<String, Role>>,
+}
+
+impl AuthLayer {
+    pub fn new(api_keys: HashMap<String, String>, roles: HashMap<String, Role>) -> Self {
+        Self {
+            api_keys: Arc::new(api_keys),
+            roles: Arc::new(roles),
+        }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = Auth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Auth {
+            inner,
+            api_keys: self.api_keys.clone(),
+            roles: self.roles.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Auth<S> {
+    inner: S,
+    api_keys: Arc<HashMap<String, String>>,
+    roles: Arc<HashMap<String, Role>>,
+}
+
+impl<S> Auth<S> {
+    /// Resolves the caller's identity and checks that its role allows the requested method.
+    fn authenticate<B>(&self, request: &Request<B>) -> Result<Option<(ClientId, Role)>, Status> {
+        if self.api_keys.is_empty() {
+            if is_admin_only(request.uri().path()) {
+                return Err(Status::permission_denied(format!(
+                    "{} requires an admin API key, and API_KEYS is not set",
+                    request.uri().path()
+                )));
+            }
+            return Ok(None);
+        }
+        let key = request
+            .headers()
+            .get(hyper::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .filter(|key| self.api_keys.contains_key(*key));
+        let key = match key {
+            Some(key) => key,
+            None if is_public(request.uri().path()) => return Ok(None),
+            None => return Err(Status::unauthenticated("missing or invalid API key")),
+        };
+        let client_id = ClientId(self.api_keys[key].clone());
+        let role = self.roles.get(key).copied().unwrap_or(Role::Reader);
+        if !role.allows(request.uri().path()) {
+            return Err(Status::permission_denied(format!(
+                "role {role:?} may not call {}",
+                request.uri().path()
+            )));
+        }
+        Ok(Some((client_id, role)))
+    }
+}
+
+impl<S, B> Service<Request<B>> for Auth<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        match self.authenticate(&request) {
+            Ok(Some((client_id, role))) => {
+                tracing::Span::current().set_attribute(ENDUSER_ID_ATTRIBUTE, client_id.0.clone());
+                request.extensions_mut().insert(client_id);
+                request.extensions_mut().insert(role);
+            }
+            Ok(None) => {}
+            Err(status) => return Box::pin(async move { Ok(status.to_http()) }),
+        }
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move { inner.call(request).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn echo_client_id(request: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        let mut response = Response::new(tonic::body::empty_body());
+        if let Some(ClientId(id)) = request.extensions().get::<ClientId>() {
+            response
+                .headers_mut()
+                .insert("x-client-id", id.parse().unwrap());
+        }
+        Ok(response)
+    }
+
+    fn layer() -> AuthLayer {
+        AuthLayer::new(
+            HashMap::from([
+                ("secret".into(), "client-a".into()),
+                ("reader-key".into(), "client-r".into()),
+                ("editor-key".into(), "client-e".into()),
+            ]),
+            HashMap::from([
+                ("reader-key".into(), Role::Reader),
+                ("editor-key".into(), Role::Editor),
+            ]),
+        )
+    }
+
+    fn request(key: &str, path: &str) -> Request<Body> {
+        Request::builder()
+            .uri(path)
+            .header("authorization", format!("Bearer {key}"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn grpc_code(response: &Response<BoxBody>) -> Option<tonic::Code> {
+        response
+            .headers()
+            .get("grpc-status")
+            .map(|status| tonic::Code::from_bytes(status.as_bytes()))
+    }
+
+    #[tokio::test]
+    async fn authenticated_request_carries_client_id() {
+        let service = layer().layer(tower::service_fn(echo_client_id));
+        let request = request("secret", "/news.NewsService/GetNews");
+
+        let response = service.oneshot(request).await.unwrap();
+
+        assert_eq!(response.headers()["x-client-id"], "client-a");
+    }
+
+    #[tokio::test]
+    async fn invalid_key_is_unauthenticated() {
+        let service = layer().layer(tower::service_fn(echo_client_id));
+        let request = request("wrong", "/news.NewsService/GetNews");
+
+        let response = service.oneshot(request).await.unwrap();
+
+        assert_eq!(grpc_code(&response), Some(tonic::Code::Unauthenticated));
+    }
+
+    #[tokio::test]
+    async fn reader_cannot_create() {
+        let service = layer().layer(tower::service_fn(echo_client_id));
+        let request = request("reader-key", "/posts.PostService/CreatePost");
+
+        let response = service.oneshot(request).await.unwrap();
+
+        assert_eq!(grpc_code(&response), Some(tonic::Code::PermissionDenied));
+    }
+
+    #[tokio::test]
+    async fn editor_can_create_but_not_call_admin_methods() {
+        let service = layer().layer(tower::service_fn(echo_client_id));
+
+        let create = service
+            .clone()
+            .oneshot(request("editor-key", "/posts.PostService/CreatePost"))
+            .await
+            .unwrap();
+        assert_eq!(grpc_code(&create), None);
+
+        let admin = service
+            .oneshot(request("editor-key", "/users.UserService/BatchPatchUsers"))
+            .await
+            .unwrap();
+        assert_eq!(grpc_code(&admin), Some(tonic::Code::PermissionDenied));
+    }
+
+    #[tokio::test]
+    async fn public_methods_need_no_key() {
+        let service = layer().layer(tower::service_fn(echo_client_id));
+        let request = Request::builder()
+            .uri("/users.UserService/GetUserLoginHint")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = service.oneshot(request).await.unwrap();
+
+        assert_eq!(grpc_code(&response), None);
+        assert!(response.headers().get("x-client-id").is_none());
+    }
+
+    #[tokio::test]
+    async fn authenticated_request_sets_enduser_id_on_span() {
+        use opentelemetry::trace::TracerProvider as _;
+        use opentelemetry_sdk::{testing::trace::InMemorySpanExporter, trace::TracerProvider};
+        use tracing::Instrument;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let exporter = InMemorySpanExporter::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _default = tracing::subscriber::set_default(subscriber);
+        let service = layer().layer(tower::service_fn(echo_client_id));
+
+        service
+            .oneshot(request("secret", "/news.NewsService/GetNews"))
+            .instrument(tracing::info_span!("request"))
+            .await
+            .unwrap();
+
+        provider.force_flush();
+        let spans = exporter.get_finished_spans().unwrap();
+        let [span] = &spans[..] else {
+            panic!("expected one span, got {}", spans.len());
+        };
+        assert!(span
+            .attributes
+            .iter()
+            .any(|kv| kv.key.as_str() == ENDUSER_ID_ATTRIBUTE && kv.value.as_str() == "client-a"));
+    }
+
+    #[tokio::test]
+    async fn admin_methods_are_denied_when_authentication_is_disabled() {
+        let service =
+            AuthLayer::new(HashMap::new(), HashMap::new()).layer(tower::service_fn(echo_client_id));
+        let request = |path: &str| Request::builder().uri(path).body(Body::empty()).unwrap();
+
+        let read = service
+            .clone()
+            .oneshot(request("/news.NewsService/GetNews"))
+            .await
+            .unwrap();
+        assert_eq!(grpc_code(&read), None);
+
+        let create = service
+            .clone()
+            .oneshot(request("/posts.PostService/CreatePost"))
+            .await
+            .unwrap();
+        assert_eq!(grpc_code(&create), None);
+
+        let admin = service
+            .oneshot(request("/diagnostics.DiagnosticsService/UndoLastChange"))
+            .await
+            .unwrap();
+        assert_eq!(grpc_code(&admin), Some(tonic::Code::PermissionDenied));
+    }
+}
@@ -0,0 +1,111 @@
+//! Converts a panicking handler into an `internal` status instead of dropping the connection.
+//!
+//! Each request runs on its own spawned task so that a panic is observed as a `JoinError`.
+//! That costs a task spawn and a cross-task wakeup per request, which latency-sensitive
+//! deployments can avoid with `CATCH_PANICS=0`; a panic then aborts the connection's stream and
+//! the client sees a transport error instead of a status.
+
+use std::task::{Context, Poll};
+
+use hyper::{Request, Response};
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+use super::BoxFuture;
+
+#[derive(Debug, Clone)]
+pub struct CatchPanicLayer {
+    enabled: bool,
+}
+
+impl CatchPanicLayer {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl<S> Layer<S> for CatchPanicLayer {
+    type Service = CatchPanic<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CatchPanic {
+            inner,
+            enabled: self.enabled,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CatchPanic<S> {
+    inner: S,
+    enabled: bool,
+}
+
+impl<S, B> Service<Request<B>> for CatchPanic<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        if !self.enabled {
+            return Box::pin(async move { inner.call(request).await });
+        }
+
+        let method = request.uri().path().to_string();
+        let handler = tokio::spawn(inner.call(request));
+        Box::pin(async move {
+            match handler.await {
+                Ok(result) => result,
+                Err(e) => {
+                    tracing::error!(method, error = %e, "handler panicked");
+                    Ok(Status::internal("internal error").to_http())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn panics(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        panic!("handler bug");
+    }
+
+    #[tokio::test]
+    async fn converts_panics_to_internal_when_enabled() {
+        let service = CatchPanicLayer::new(true).layer(tower::service_fn(panics));
+
+        let response = service.oneshot(Request::new(Body::empty())).await.unwrap();
+
+        let code = tonic::Code::from_bytes(response.headers()["grpc-status"].as_bytes());
+        assert_eq!(code, tonic::Code::Internal);
+    }
+
+    #[tokio::test]
+    async fn propagates_panics_when_disabled() {
+        let service = CatchPanicLayer::new(false).layer(tower::service_fn(panics));
+
+        let outcome = tokio::spawn(service.oneshot(Request::new(Body::empty()))).await;
+
+        assert!(outcome.unwrap_err().is_panic());
+    }
+}
@@ -0,0 +1,149 @@
+//! Delays a random fraction of requests by up to `CHAOS_MAX_LATENCY_MS`, so clients can
+//! exercise their timeout handling against a deployed server. Enabled with `CHAOS_MODE=1`.
+
+use std::{
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use hyper::{Request, Response};
+use rand::{rngs::StdRng, Rng};
+use tonic::body::BoxBody;
+use tower::{Layer, Service};
+
+use super::BoxFuture;
+
+#[derive(Debug, Clone)]
+pub struct ChaosLayer {
+    enabled: bool,
+    fraction: f64,
+    max_latency: Duration,
+    rng: Arc<Mutex<StdRng>>,
+}
+
+impl ChaosLayer {
+    pub fn new(
+        enabled: bool,
+        fraction: f64,
+        max_latency: Duration,
+        rng: Arc<Mutex<StdRng>>,
+    ) -> Self {
+        Self {
+            enabled,
+            fraction,
+            max_latency,
+            rng,
+        }
+    }
+}
+
+impl<S> Layer<S> for ChaosLayer {
+    type Service = Chaos<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Chaos {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Chaos<S> {
+    inner: S,
+    layer: ChaosLayer,
+}
+
+impl<S> Chaos<S> {
+    /// Latency to inject into the next request, if it was selected.
+    fn sample_delay(&self) -> Option<Duration> {
+        if !self.layer.enabled {
+            return None;
+        }
+        let mut rng = self.layer.rng.lock().unwrap();
+        if rng.gen::<f64>() >= self.layer.fraction {
+            return None;
+        }
+        Some(rng.gen_range(Duration::ZERO..=self.layer.max_latency))
+    }
+}
+
+impl<S, B> Service<Request<B>> for Chaos<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let delay = self.sample_delay();
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            if let Some(delay) = delay {
+                tracing::debug!(method = request.uri().path(), ?delay, "injecting latency");
+                tokio::time::sleep(delay).await;
+            }
+            inner.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, time::Instant};
+
+    use hyper::Body;
+    use rand::SeedableRng;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn ok(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        Ok(Response::new(tonic::body::empty_body()))
+    }
+
+    fn chaos(enabled: bool, fraction: f64, max_latency: Duration) -> Chaos<()> {
+        let rng = Arc::new(Mutex::new(StdRng::seed_from_u64(7)));
+        ChaosLayer::new(enabled, fraction, max_latency, rng).layer(())
+    }
+
+    #[test]
+    fn delays_every_request_within_bounds_at_full_fraction() {
+        let max_latency = Duration::from_millis(50);
+        let service = chaos(true, 1.0, max_latency);
+
+        for _ in 0..100 {
+            let delay = service.sample_delay().unwrap();
+            assert!(delay <= max_latency, "{delay:?} exceeds {max_latency:?}");
+        }
+    }
+
+    #[test]
+    fn never_delays_when_disabled() {
+        let service = chaos(false, 1.0, Duration::from_millis(50));
+
+        assert_eq!(service.sample_delay(), None);
+    }
+
+    #[tokio::test]
+    async fn injected_latency_delays_the_response() {
+        let max_latency = Duration::from_millis(20);
+        let rng = Arc::new(Mutex::new(StdRng::seed_from_u64(7)));
+        let expected = chaos(true, 1.0, max_latency).sample_delay().unwrap();
+        let service = ChaosLayer::new(true, 1.0, max_latency, rng).layer(tower::service_fn(ok));
+
+        let start = Instant::now();
+        service.oneshot(Request::new(Body::empty())).await.unwrap();
+
+        assert!(start.elapsed() >= expected);
+    }
+}
@@ -0,0 +1,166 @@
+//! Fails fast with `unavailable` while the storage circuit breaker is open.
+
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use hyper::{Request, Response};
+use tonic::{body::BoxBody, Code, Status};
+use tower::{Layer, Service};
+
+use super::BoxFuture;
+use crate::circuit_breaker::CircuitBreaker;
+
+/// Codes that signal a failing store rather than a bad request. A poisoned store lock
+/// surfaces as `Internal` once `CatchPanicLayer` has caught the panic.
+const STORAGE_FAILURES: &[Code] = &[Code::Internal, Code::Unavailable, Code::DataLoss];
+
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerLayer {
+    /// Disabled when `None`.
+    breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl CircuitBreakerLayer {
+    pub fn new(breaker: Option<Arc<CircuitBreaker>>) -> Self {
+        Self { breaker }
+    }
+}
+
+impl<S> Layer<S> for CircuitBreakerLayer {
+    type Service = Breaker<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Breaker {
+            inner,
+            breaker: self.breaker.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Breaker<S> {
+    inner: S,
+    breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl<S, B> Service<Request<B>> for Breaker<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let Some(breaker) = self.breaker.clone() else {
+            return Box::pin(inner.call(request));
+        };
+        Box::pin(async move {
+            // Held across the call so a probe dropped by a timeout or cancellation still
+            // reports back.
+            let Some(permit) = breaker.acquire(Instant::now()) else {
+                return Ok(Status::unavailable("storage circuit breaker is open").to_http());
+            };
+            let response = inner.call(request).await?;
+            let code = response
+                .headers()
+                .get("grpc-status")
+                .map_or(Code::Ok, |code| Code::from_bytes(code.as_bytes()));
+            permit.finish(!STORAGE_FAILURES.contains(&code), Instant::now());
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, time::Duration};
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::circuit_breaker::BreakerState;
+
+    async fn failing_store(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        Ok(Status::internal("store unavailable").to_http())
+    }
+
+    fn request() -> Request<Body> {
+        Request::builder()
+            .uri("/news.NewsService/GetNews")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn code(response: &Response<BoxBody>) -> Code {
+        Code::from_bytes(response.headers()["grpc-status"].as_bytes())
+    }
+
+    #[tokio::test]
+    async fn opens_on_failing_store_then_half_opens() {
+        let breaker = Arc::new(CircuitBreaker::new(2, Duration::from_millis(50)));
+        let service =
+            CircuitBreakerLayer::new(Some(breaker.clone())).layer(tower::service_fn(failing_store));
+
+        for _ in 0..2 {
+            let response = service.clone().oneshot(request()).await.unwrap();
+            assert_eq!(code(&response), Code::Internal);
+        }
+        assert_eq!(breaker.state(Instant::now()), BreakerState::Open);
+        let response = service.clone().oneshot(request()).await.unwrap();
+        assert_eq!(code(&response), Code::Unavailable);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(breaker.state(Instant::now()), BreakerState::HalfOpen);
+        // The probe reaches the store again, and its failure reopens the breaker.
+        let response = service.oneshot(request()).await.unwrap();
+        assert_eq!(code(&response), Code::Internal);
+        assert_eq!(breaker.state(Instant::now()), BreakerState::Open);
+    }
+
+    #[tokio::test]
+    async fn dropped_probe_does_not_block_later_probes() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let store = {
+            let calls = calls.clone();
+            tower::service_fn(move |_: Request<Body>| {
+                let call = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                async move {
+                    if call == 1 {
+                        // The probe hangs until the caller gives up on it.
+                        std::future::pending::<()>().await;
+                    }
+                    Ok::<_, Infallible>(Status::internal("store unavailable").to_http())
+                }
+            })
+        };
+        let breaker = Arc::new(CircuitBreaker::new(1, Duration::ZERO));
+        let service = CircuitBreakerLayer::new(Some(breaker)).layer(store);
+
+        service.clone().oneshot(request()).await.unwrap();
+        let probe = service.clone().oneshot(request());
+        assert!(tokio::time::timeout(Duration::from_millis(10), probe)
+            .await
+            .is_err());
+
+        let response = service.oneshot(request()).await.unwrap();
+        assert_eq!(
+            code(&response),
+            Code::Internal,
+            "a new probe reached the store"
+        );
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+}
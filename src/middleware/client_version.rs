@@ -0,0 +1,154 @@
+//! Rejects clients older than `MIN_CLIENT_VERSION`, as reported in `x-client-version`.
+
+use std::task::{Context, Poll};
+
+use hyper::{Request, Response};
+use semver::Version;
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+use super::BoxFuture;
+
+const CLIENT_VERSION_HEADER: &str = "x-client-version";
+
+#[derive(Debug, Clone)]
+pub struct MinClientVersionLayer {
+    /// Every version is accepted when `None`.
+    min: Option<Version>,
+    allow_missing: bool,
+}
+
+impl MinClientVersionLayer {
+    pub fn new(min: Option<Version>, allow_missing: bool) -> Self {
+        Self { min, allow_missing }
+    }
+}
+
+impl<S> Layer<S> for MinClientVersionLayer {
+    type Service = MinClientVersion<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MinClientVersion {
+            inner,
+            min: self.min.clone(),
+            allow_missing: self.allow_missing,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MinClientVersion<S> {
+    inner: S,
+    min: Option<Version>,
+    allow_missing: bool,
+}
+
+impl<S> MinClientVersion<S> {
+    fn check<B>(&self, request: &Request<B>) -> Result<(), Status> {
+        let Some(min) = &self.min else {
+            return Ok(());
+        };
+        let Some(header) = request.headers().get(CLIENT_VERSION_HEADER) else {
+            if self.allow_missing {
+                return Ok(());
+            }
+            return Err(Status::failed_precondition(format!(
+                "missing {CLIENT_VERSION_HEADER}; clients must be version {min} or newer"
+            )));
+        };
+        let version = header
+            .to_str()
+            .ok()
+            .and_then(|v| Version::parse(v.trim()).ok())
+            .ok_or_else(|| {
+                Status::invalid_argument(format!("{CLIENT_VERSION_HEADER} is not a valid semver"))
+            })?;
+        if version < *min {
+            return Err(Status::failed_precondition(format!(
+                "client version {version} is no longer supported; please upgrade to {min} or newer"
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<S, B> Service<Request<B>> for MinClientVersion<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        if let Err(status) = self.check(&request) {
+            return Box::pin(async move { Ok(status.to_http()) });
+        }
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move { inner.call(request).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn ok(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        Ok(Response::new(tonic::body::empty_body()))
+    }
+
+    async fn status(allow_missing: bool, version: Option<&str>) -> Option<tonic::Code> {
+        let service = MinClientVersionLayer::new(Some(Version::new(2, 1, 0)), allow_missing)
+            .layer(tower::service_fn(ok));
+        let mut request = Request::builder();
+        if let Some(version) = version {
+            request = request.header(CLIENT_VERSION_HEADER, version);
+        }
+
+        let response = service
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        response
+            .headers()
+            .get("grpc-status")
+            .map(|code| tonic::Code::from_bytes(code.as_bytes()))
+    }
+
+    #[tokio::test]
+    async fn rejects_too_old_clients() {
+        assert_eq!(
+            status(true, Some("2.0.9")).await,
+            Some(tonic::Code::FailedPrecondition)
+        );
+    }
+
+    #[tokio::test]
+    async fn accepts_current_clients() {
+        assert_eq!(status(false, Some("2.1.0")).await, None);
+        assert_eq!(status(false, Some("3.0.0-beta.1")).await, None);
+    }
+
+    #[tokio::test]
+    async fn missing_header_follows_configuration() {
+        assert_eq!(status(true, None).await, None);
+        assert_eq!(
+            status(false, None).await,
+            Some(tonic::Code::FailedPrecondition)
+        );
+    }
+}
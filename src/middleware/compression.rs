@@ -0,0 +1,234 @@
+//! Gzip-compresses response messages at or above a size threshold.
+//!
+//! gRPC flags compression per message, so messages below `COMPRESSION_MIN_BYTES` are sent with
+//! the compressed flag cleared even though the response advertises `grpc-encoding: gzip`.
+
+use std::{
+    io::Write,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use flate2::{write::GzEncoder, Compression as Level};
+
+use hyper::{
+    body::{Bytes, HttpBody},
+    header::HeaderValue,
+    HeaderMap, Request, Response,
+};
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+use super::BoxFuture;
+
+/// Length of the gRPC message prefix: a compression flag followed by a big-endian u32 length.
+const FRAME_HEADER_LEN: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct CompressionLayer {
+    enabled: bool,
+    min_bytes: usize,
+}
+
+impl CompressionLayer {
+    pub fn new(enabled: bool, min_bytes: usize) -> Self {
+        Self { enabled, min_bytes }
+    }
+}
+
+impl<S> Layer<S> for CompressionLayer {
+    type Service = Compression<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Compression {
+            inner,
+            enabled: self.enabled,
+            min_bytes: self.min_bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Compression<S> {
+    inner: S,
+    enabled: bool,
+    min_bytes: usize,
+}
+
+impl<S, B> Service<Request<B>> for Compression<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let accepts_gzip = request
+            .headers()
+            .get("grpc-accept-encoding")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.split(',').any(|e| e.trim() == "gzip"));
+        let compress = self.enabled && accepts_gzip;
+        let min_bytes = self.min_bytes;
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let mut response = inner.call(request).await?;
+            // Leave responses alone if the handler already chose an encoding.
+            if !compress || response.headers().contains_key("grpc-encoding") {
+                return Ok(response);
+            }
+            response
+                .headers_mut()
+                .insert("grpc-encoding", HeaderValue::from_static("gzip"));
+            Ok(response.map(|body| {
+                BoxBody::new(CompressingBody {
+                    inner: body,
+                    buffer: Vec::new(),
+                    min_bytes,
+                })
+            }))
+        })
+    }
+}
+
+/// Re-frames the inner body, compressing each uncompressed message of at least `min_bytes`.
+struct CompressingBody {
+    inner: BoxBody,
+    /// Bytes received but not yet forming a complete message.
+    buffer: Vec<u8>,
+    min_bytes: usize,
+}
+
+impl CompressingBody {
+    /// Removes the first complete message from the buffer, compressing it if large enough.
+    fn next_frame(&mut self) -> Option<Bytes> {
+        let header = self.buffer.get(..FRAME_HEADER_LEN)?;
+        let len = u32::from_be_bytes(header[1..].try_into().unwrap()) as usize;
+        if self.buffer.len() < FRAME_HEADER_LEN + len {
+            return None;
+        }
+        let frame: Vec<u8> = self.buffer.drain(..FRAME_HEADER_LEN + len).collect();
+        let (flag, payload) = (frame[0], &frame[FRAME_HEADER_LEN..]);
+        if flag != 0 || payload.len() < self.min_bytes {
+            return Some(frame.into());
+        }
+        let compressed = gzip(payload);
+        let mut out = Vec::with_capacity(FRAME_HEADER_LEN + compressed.len());
+        out.push(1);
+        out.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        out.extend_from_slice(&compressed);
+        Some(out.into())
+    }
+}
+
+impl HttpBody for CompressingBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        loop {
+            if let Some(frame) = self.next_frame() {
+                return Poll::Ready(Some(Ok(frame)));
+            }
+            match Pin::new(&mut self.inner).poll_data(cx) {
+                Poll::Ready(Some(Ok(chunk))) => self.buffer.extend_from_slice(&chunk),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) if self.buffer.is_empty() => return Poll::Ready(None),
+                // A truncated trailing message is passed through for the client to reject.
+                Poll::Ready(None) => {
+                    let rest = std::mem::take(&mut self.buffer);
+                    return Poll::Ready(Some(Ok(rest.into())));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.buffer.is_empty() && self.inner.is_end_stream()
+    }
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+    // Writing into a Vec cannot fail.
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn framed(len: usize) -> Vec<u8> {
+        let mut frame = vec![0];
+        frame.extend_from_slice(&(len as u32).to_be_bytes());
+        frame.resize(FRAME_HEADER_LEN + len, b'a');
+        frame
+    }
+
+    async fn respond(len: usize) -> Response<Bytes> {
+        let service = CompressionLayer::new(true, 1024).layer(tower::service_fn(
+            move |_: Request<Body>| async move {
+                let body = Body::from(framed(len)).map_err(|e| Status::from_error(e.into()));
+                Ok::<_, std::convert::Infallible>(Response::new(body.boxed_unsync()))
+            },
+        ));
+        let request = Request::builder()
+            .header("grpc-accept-encoding", "identity, gzip")
+            .body(Body::empty())
+            .unwrap();
+
+        let (parts, body) = service.oneshot(request).await.unwrap().into_parts();
+        Response::from_parts(parts, hyper::body::to_bytes(body).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn leaves_small_messages_uncompressed() {
+        let response = respond(100).await;
+
+        assert_eq!(response.body().as_ref(), framed(100).as_slice());
+    }
+
+    #[tokio::test]
+    async fn compresses_large_messages() {
+        let response = respond(4096).await;
+
+        assert_eq!(response.headers()["grpc-encoding"], "gzip");
+        let body = response.body();
+        assert_eq!(body[0], 1);
+        let len = u32::from_be_bytes(body[1..5].try_into().unwrap()) as usize;
+        assert_eq!(body.len(), FRAME_HEADER_LEN + len);
+        assert!(len < 4096);
+        let mut message = Vec::new();
+        GzDecoder::new(&body[FRAME_HEADER_LEN..])
+            .read_to_end(&mut message)
+            .unwrap();
+        assert_eq!(message, &framed(4096)[FRAME_HEADER_LEN..]);
+    }
+}
@@ -0,0 +1,149 @@
+//! Limits concurrent RPCs and records queueing time separately from handler time.
+
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use hyper::{Request, Response};
+use tokio::sync::Semaphore;
+use tonic::body::BoxBody;
+use tower::{Layer, Service};
+
+use super::BoxFuture;
+use crate::metrics::Metrics;
+
+/// Milliseconds a request waited for a concurrency slot.
+pub const QUEUE_WAIT_METRIC: &str = "grpc_queue_wait_ms";
+/// Milliseconds spent in the handler once a slot was acquired.
+pub const SERVICE_TIME_METRIC: &str = "grpc_service_time_ms";
+
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLayer {
+    metrics: Arc<Metrics>,
+    /// Unlimited when `None`; wait time is then always zero.
+    permits: Option<Arc<Semaphore>>,
+}
+
+impl ConcurrencyLayer {
+    pub fn new(metrics: Arc<Metrics>, max_concurrent: Option<usize>) -> Self {
+        Self {
+            metrics,
+            permits: max_concurrent.map(|max| Arc::new(Semaphore::new(max))),
+        }
+    }
+}
+
+impl<S> Layer<S> for ConcurrencyLayer {
+    type Service = Concurrency<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Concurrency {
+            inner,
+            metrics: self.metrics.clone(),
+            permits: self.permits.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Concurrency<S> {
+    inner: S,
+    metrics: Arc<Metrics>,
+    permits: Option<Arc<Semaphore>>,
+}
+
+impl<S, B> Service<Request<B>> for Concurrency<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let metrics = self.metrics.clone();
+        let permits = self.permits.clone();
+
+        Box::pin(async move {
+            let method = request.uri().path().to_string();
+            let queued_at = Instant::now();
+            let _permit = match &permits {
+                // The semaphore is never closed, so acquiring cannot fail.
+                Some(permits) => Some(permits.acquire().await.unwrap()),
+                None => None,
+            };
+            let started_at = Instant::now();
+            metrics.record(
+                QUEUE_WAIT_METRIC,
+                &[("method", &method)],
+                millis_between(queued_at, started_at),
+            );
+
+            let response = inner.call(request).await;
+            metrics.record(
+                SERVICE_TIME_METRIC,
+                &[("method", &method)],
+                millis_between(started_at, Instant::now()),
+            );
+            response
+        })
+    }
+}
+
+fn millis_between(start: Instant, end: Instant) -> f64 {
+    end.duration_since(start).as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, time::Duration};
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn slow(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        Ok(Response::new(tonic::body::empty_body()))
+    }
+
+    fn request() -> Request<Body> {
+        Request::builder()
+            .uri("/posts.PostService/ListPosts")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn records_wait_time_when_saturated() {
+        let metrics = Arc::new(Metrics::default());
+        let service =
+            ConcurrencyLayer::new(metrics.clone(), Some(1)).layer(tower::service_fn(slow));
+
+        let (first, second) = tokio::join!(
+            service.clone().oneshot(request()),
+            service.clone().oneshot(request())
+        );
+        first.unwrap();
+        second.unwrap();
+
+        let labels = [("method", "/posts.PostService/ListPosts")];
+        let wait = metrics.histogram(QUEUE_WAIT_METRIC, &labels).unwrap();
+        assert_eq!(wait.count, 2);
+        assert!(wait.max > 0.0);
+        let service_time = metrics.histogram(SERVICE_TIME_METRIC, &labels).unwrap();
+        assert_eq!(service_time.count, 2);
+        assert!(service_time.min >= 20.0);
+    }
+}
@@ -0,0 +1,154 @@
+//! Counts failed RPCs per method and status code, and always traces them.
+
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use hyper::{Request, Response};
+use opentelemetry::{
+    global,
+    trace::{Span, Tracer},
+    KeyValue,
+};
+use tonic::body::BoxBody;
+use tower::{Layer, Service};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use super::BoxFuture;
+use crate::{metrics::Metrics, telemetry::FORCE_SAMPLE_ATTRIBUTE};
+
+/// One observation per failed RPC, labeled by method and status code name.
+pub const ERRORS_METRIC: &str = "grpc_errors_total";
+
+#[derive(Debug, Clone)]
+pub struct ErrorMetricsLayer {
+    metrics: Arc<Metrics>,
+}
+
+impl ErrorMetricsLayer {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S> Layer<S> for ErrorMetricsLayer {
+    type Service = ErrorMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ErrorMetrics {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ErrorMetrics<S> {
+    inner: S,
+    metrics: Arc<Metrics>,
+}
+
+impl<S, B> Service<Request<B>> for ErrorMetrics<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let method = request.uri().path().to_string();
+        let metrics = self.metrics.clone();
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let response = inner.call(request).await?;
+            // Like the access log, only errors in the response head are seen, which covers
+            // every unary failure.
+            let code = response
+                .headers()
+                .get("grpc-status")
+                .map_or(tonic::Code::Ok, |code| {
+                    tonic::Code::from_bytes(code.as_bytes())
+                });
+            if code != tonic::Code::Ok {
+                let code = format!("{code:?}");
+                metrics.record(ERRORS_METRIC, &[("method", &method), ("code", &code)], 1.0);
+                trace_error(method, code);
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Emits a span for the failure that the sampler keeps even when the request's own trace
+/// was not sampled. It joins the request's trace when there is one.
+fn trace_error(method: String, code: String) {
+    let parent = tracing::Span::current().context();
+    let tracer = global::tracer("grpc-errors");
+    let mut span = tracer
+        .span_builder("grpc.error")
+        .with_attributes([
+            KeyValue::new(FORCE_SAMPLE_ATTRIBUTE, true),
+            KeyValue::new("rpc.method", method),
+            KeyValue::new("rpc.grpc.status_code", code),
+        ])
+        .start_with_context(&tracer, &parent);
+    span.end();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tonic::Status;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn not_found(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        Ok(Status::not_found("missing").to_http())
+    }
+
+    async fn ok(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        Ok(Response::new(tonic::body::empty_body()))
+    }
+
+    fn request() -> Request<Body> {
+        Request::builder()
+            .uri("/news.NewsService/GetNews")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn counts_errors_by_method_and_code() {
+        let metrics = Arc::new(Metrics::default());
+        let failing = ErrorMetricsLayer::new(metrics.clone()).layer(tower::service_fn(not_found));
+        let succeeding = ErrorMetricsLayer::new(metrics.clone()).layer(tower::service_fn(ok));
+
+        failing.oneshot(request()).await.unwrap();
+        succeeding.oneshot(request()).await.unwrap();
+
+        let errors = metrics
+            .histogram(
+                ERRORS_METRIC,
+                &[
+                    ("method", "/news.NewsService/GetNews"),
+                    ("code", "NotFound"),
+                ],
+            )
+            .unwrap();
+        assert_eq!(errors.count, 1);
+        assert_eq!(metrics.histograms().len(), 1);
+    }
+}
@@ -0,0 +1,142 @@
+//! Rejects grpc-web requests whose content-type is not `application/grpc-web*`, which would
+//! otherwise fail later with an opaque decode error.
+
+use std::task::{Context, Poll};
+
+use hyper::{header::CONTENT_TYPE, Request, Response, Version};
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+use super::BoxFuture;
+
+/// Sent by grpc-web clients, which may also reach the server over HTTP/2.
+const GRPC_WEB_HEADER: &str = "x-grpc-web";
+
+#[derive(Debug, Clone)]
+pub struct GrpcWebContentTypeLayer {
+    enabled: bool,
+}
+
+impl GrpcWebContentTypeLayer {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl<S> Layer<S> for GrpcWebContentTypeLayer {
+    type Service = GrpcWebContentType<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcWebContentType {
+            inner,
+            enabled: self.enabled,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GrpcWebContentType<S> {
+    inner: S,
+    enabled: bool,
+}
+
+impl<S, B> Service<Request<B>> for GrpcWebContentType<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        // Native gRPC requires HTTP/2, so anything older can only be grpc-web.
+        let grpc_web =
+            request.version() < Version::HTTP_2 || request.headers().contains_key(GRPC_WEB_HEADER);
+        if self.enabled && grpc_web {
+            let content_type = request
+                .headers()
+                .get(CONTENT_TYPE)
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+            if !content_type
+                .as_deref()
+                .is_some_and(|value| value.starts_with("application/grpc-web"))
+            {
+                let status = Status::invalid_argument(format!(
+                    "grpc-web requests must use content-type application/grpc-web or \
+                     application/grpc-web-text, got {}",
+                    content_type.map_or("none".into(), |value| format!("`{value}`"))
+                ));
+                return Box::pin(async move { Ok(status.to_http()) });
+            }
+        }
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move { inner.call(request).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn ok(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        Ok(Response::new(tonic::body::empty_body()))
+    }
+
+    fn request(version: Version, content_type: &str) -> Request<Body> {
+        Request::builder()
+            .version(version)
+            .uri("/news.NewsService/GetNews")
+            .header(CONTENT_TYPE, content_type)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn rejects_grpc_web_request_with_wrong_content_type() {
+        let service = GrpcWebContentTypeLayer::new(true).layer(tower::service_fn(ok));
+
+        let response = service
+            .oneshot(request(Version::HTTP_11, "application/json"))
+            .await
+            .unwrap();
+
+        let code = tonic::Code::from_bytes(response.headers()["grpc-status"].as_bytes());
+        assert_eq!(code, tonic::Code::InvalidArgument);
+        let message = response.headers()["grpc-message"].to_str().unwrap();
+        assert!(message.contains("application/grpc-web"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn allows_grpc_web_and_native_grpc() {
+        for (version, content_type) in [
+            (Version::HTTP_11, "application/grpc-web+proto"),
+            (Version::HTTP_11, "application/grpc-web-text"),
+            (Version::HTTP_2, "application/grpc"),
+        ] {
+            let service = GrpcWebContentTypeLayer::new(true).layer(tower::service_fn(ok));
+
+            let response = service
+                .oneshot(request(version, content_type))
+                .await
+                .unwrap();
+
+            assert!(
+                !response.headers().contains_key("grpc-status"),
+                "{content_type}"
+            );
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Counts RPCs that are still running, including streams whose response body is still open.
+
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use hyper::{
+    body::{Bytes, HttpBody, SizeHint},
+    HeaderMap, Request, Response,
+};
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+use super::BoxFuture;
+
+#[derive(Debug, Clone)]
+pub struct InFlightLayer {
+    active: Arc<AtomicUsize>,
+}
+
+impl InFlightLayer {
+    pub fn new(active: Arc<AtomicUsize>) -> Self {
+        Self { active }
+    }
+}
+
+impl<S> Layer<S> for InFlightLayer {
+    type Service = InFlight<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        InFlight {
+            inner,
+            active: self.active.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InFlight<S> {
+    inner: S,
+    active: Arc<AtomicUsize>,
+}
+
+impl<S, B> Service<Request<B>> for InFlight<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        self.active.fetch_add(1, Ordering::SeqCst);
+        let guard = ActiveGuard(self.active.clone());
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let response = inner.call(request).await?;
+            // The RPC stays active until its body, which may be a long-lived stream, is dropped.
+            Ok(response.map(|body| {
+                BoxBody::new(TrackedBody {
+                    inner: body,
+                    _guard: guard,
+                })
+            }))
+        })
+    }
+}
+
+/// Decrements the active count when dropped.
+struct ActiveGuard(Arc<AtomicUsize>);
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct TrackedBody {
+    inner: BoxBody,
+    _guard: ActiveGuard,
+}
+
+impl HttpBody for TrackedBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Pin::new(&mut self.inner).poll_data(cx)
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn ok(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        Ok(Response::new(tonic::body::empty_body()))
+    }
+
+    #[tokio::test]
+    async fn counts_requests_until_body_is_dropped() {
+        let active = Arc::new(AtomicUsize::new(0));
+        let service = InFlightLayer::new(active.clone()).layer(tower::service_fn(ok));
+
+        let response = service.oneshot(Request::new(Body::empty())).await.unwrap();
+        assert_eq!(active.load(Ordering::SeqCst), 1);
+
+        drop(response);
+        assert_eq!(active.load(Ordering::SeqCst), 0);
+    }
+}
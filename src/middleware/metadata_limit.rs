@@ -0,0 +1,162 @@
+//! Rejects requests whose metadata exceeds `MAX_METADATA_ENTRIES` or `MAX_METADATA_BYTES`.
+
+use std::task::{Context, Poll};
+
+use hyper::{Request, Response};
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+use super::BoxFuture;
+
+#[derive(Debug, Clone)]
+pub struct MetadataLimitLayer {
+    max_entries: usize,
+    max_bytes: usize,
+}
+
+impl MetadataLimitLayer {
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            max_entries,
+            max_bytes,
+        }
+    }
+}
+
+impl<S> Layer<S> for MetadataLimitLayer {
+    type Service = MetadataLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetadataLimit {
+            inner,
+            max_entries: self.max_entries,
+            max_bytes: self.max_bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MetadataLimit<S> {
+    inner: S,
+    max_entries: usize,
+    max_bytes: usize,
+}
+
+impl<S> MetadataLimit<S> {
+    fn check<B>(&self, request: &Request<B>) -> Result<(), Status> {
+        let headers = request.headers();
+        if headers.len() > self.max_entries {
+            return Err(Status::invalid_argument(format!(
+                "request has {} metadata entries; at most {} are allowed",
+                headers.len(),
+                self.max_entries
+            )));
+        }
+        // Sized like HTTP/2 header lists, without the per-entry overhead.
+        let bytes: usize = headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len())
+            .sum();
+        if bytes > self.max_bytes {
+            return Err(Status::invalid_argument(format!(
+                "request metadata of {bytes} bytes exceeds the limit of {} bytes",
+                self.max_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<S, B> Service<Request<B>> for MetadataLimit<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        if let Err(status) = self.check(&request) {
+            tracing::warn!(
+                method = request.uri().path(),
+                "rejecting oversized metadata"
+            );
+            return Box::pin(async move { Ok(status.to_http()) });
+        }
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move { inner.call(request).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn ok(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        Ok(Response::new(tonic::body::empty_body()))
+    }
+
+    async fn status(
+        layer: MetadataLimitLayer,
+        headers: &[(String, String)],
+    ) -> Option<tonic::Code> {
+        let service = layer.layer(tower::service_fn(ok));
+        let mut request = Request::builder();
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+
+        let response = service
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        response
+            .headers()
+            .get("grpc-status")
+            .map(|code| tonic::Code::from_bytes(code.as_bytes()))
+    }
+
+    #[tokio::test]
+    async fn rejects_too_many_entries() {
+        let headers: Vec<_> = (0..5)
+            .map(|i| (format!("x-entry-{i}"), "v".to_string()))
+            .collect();
+
+        assert_eq!(
+            status(MetadataLimitLayer::new(4, 1024), &headers[..4]).await,
+            None
+        );
+        assert_eq!(
+            status(MetadataLimitLayer::new(4, 1024), &headers).await,
+            Some(tonic::Code::InvalidArgument)
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_too_many_bytes() {
+        let headers = [("x-large".to_string(), "v".repeat(100))];
+
+        assert_eq!(
+            status(MetadataLimitLayer::new(4, 107), &headers).await,
+            None
+        );
+        assert_eq!(
+            status(MetadataLimitLayer::new(4, 106), &headers).await,
+            Some(tonic::Code::InvalidArgument)
+        );
+    }
+}
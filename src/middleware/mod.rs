@@ -0,0 +1,103 @@
+//! Tower layers applied to every gRPC request in `bind()`.
+
+use std::{future::Future, pin::Pin};
+
+pub mod access_log;
+pub mod audit;
+pub mod auth;
+pub mod catch_panic;
+pub mod chaos;
+pub mod circuit_breaker;
+pub mod client_version;
+pub mod compression;
+pub mod concurrency;
+pub mod error_metrics;
+pub mod grpc_web_content_type;
+pub mod in_flight;
+pub mod metadata_limit;
+pub mod read_only;
+pub mod request_id;
+pub mod request_size;
+pub mod timeout;
+pub mod tls_only;
+pub mod trace_trust;
+
+pub use access_log::{AccessLog, AccessLogLayer};
+pub use audit::AuditLayer;
+pub use auth::{AuthLayer, ClientId, Role};
+pub use catch_panic::CatchPanicLayer;
+pub use chaos::ChaosLayer;
+pub use circuit_breaker::CircuitBreakerLayer;
+pub use client_version::MinClientVersionLayer;
+pub use compression::CompressionLayer;
+pub use concurrency::ConcurrencyLayer;
+pub use error_metrics::ErrorMetricsLayer;
+pub use grpc_web_content_type::GrpcWebContentTypeLayer;
+pub use in_flight::InFlightLayer;
+pub use metadata_limit::MetadataLimitLayer;
+pub use read_only::ReadOnlyLayer;
+pub use request_id::RequestIdLayer;
+pub use request_size::RequestSizeLayer;
+pub use timeout::MethodTimeoutLayer;
+pub use tls_only::{ConnectionInfo, TlsOnlyLayer};
+pub use trace_trust::TraceTrustLayer;
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// Full paths of every RPC that modifies stored data.
+const MUTATING_METHODS: &[&str] = &[
+    "/news.NewsService/AddNews",
+    "/news.NewsService/EditNews",
+    "/news.NewsService/DeleteNews",
+    "/news.NewsService/DeleteNewsIfUnpublished",
+    "/news.NewsService/PublishNews",
+    "/news.NewsService/ConvertNewsToPost",
+    "/news.NewsService/ReindexNews",
+    "/posts.PostService/CreatePost",
+    "/posts.PostService/UpdatePost",
+    "/posts.PostService/DeletePost",
+    "/posts.PostService/DeletePostsByUser",
+    "/posts.PostService/MoveUserPosts",
+    "/posts.PostService/TransferPost",
+    "/users.UserService/CreateUser",
+    "/users.UserService/PatchUser",
+    "/users.UserService/BatchPatchUsers",
+    "/users.UserService/DeleteUser",
+    "/diagnostics.DiagnosticsService/UndoLastChange",
+];
+
+/// Full paths of the RPCs restricted to the admin role.
+const ADMIN_METHODS: &[&str] = &[
+    "/news.NewsService/ConvertNewsToPost",
+    "/news.NewsService/ReindexNews",
+    "/users.UserService/BatchPatchUsers",
+    "/diagnostics.DiagnosticsService/GetAuditLog",
+    "/diagnostics.DiagnosticsService/UndoLastChange",
+    "/diagnostics.DiagnosticsService/GetOrphanedPosts",
+    "/diagnostics.DiagnosticsService/GetDuplicatePostTitles",
+];
+
+/// Full paths of the RPCs callable without an API key even when authentication is enabled.
+const PUBLIC_METHODS: &[&str] = &["/users.UserService/GetUserLoginHint"];
+
+/// Full paths of the RPCs that must only run over an encrypted connection.
+const SENSITIVE_METHODS: &[&str] = &[
+    "/users.UserService/PatchUser",
+    "/users.UserService/BatchPatchUsers",
+];
+
+pub fn is_mutating(path: &str) -> bool {
+    MUTATING_METHODS.contains(&path)
+}
+
+pub fn is_admin_only(path: &str) -> bool {
+    ADMIN_METHODS.contains(&path)
+}
+
+pub fn is_public(path: &str) -> bool {
+    PUBLIC_METHODS.contains(&path)
+}
+
+pub fn is_sensitive(path: &str) -> bool {
+    SENSITIVE_METHODS.contains(&path) || is_admin_only(path)
+}
@@ -0,0 +1,106 @@
+//! Read-replica mode: rejects every mutating RPC while reads keep working.
+
+use std::task::{Context, Poll};
+
+use hyper::{Request, Response};
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+use super::{is_mutating, BoxFuture};
+
+#[derive(Debug, Clone)]
+pub struct ReadOnlyLayer {
+    enabled: bool,
+}
+
+impl ReadOnlyLayer {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl<S> Layer<S> for ReadOnlyLayer {
+    type Service = ReadOnly<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ReadOnly {
+            inner,
+            enabled: self.enabled,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadOnly<S> {
+    inner: S,
+    enabled: bool,
+}
+
+impl<S, B> Service<Request<B>> for ReadOnly<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        if self.enabled && is_mutating(request.uri().path()) {
+            let status = Status::failed_precondition("instance is read-only");
+            return Box::pin(async move { Ok(status.to_http()) });
+        }
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move { inner.call(request).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn ok(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        Ok(Response::new(tonic::body::empty_body()))
+    }
+
+    fn request(path: &str) -> Request<Body> {
+        Request::builder().uri(path).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn rejects_writes_when_read_only() {
+        let service = ReadOnlyLayer::new(true).layer(tower::service_fn(ok));
+
+        let response = service
+            .oneshot(request("/posts.PostService/CreatePost"))
+            .await
+            .unwrap();
+
+        let code = tonic::Code::from_bytes(response.headers()["grpc-status"].as_bytes());
+        assert_eq!(code, tonic::Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn allows_reads_when_read_only() {
+        let service = ReadOnlyLayer::new(true).layer(tower::service_fn(ok));
+
+        let response = service
+            .oneshot(request("/posts.PostService/GetPost"))
+            .await
+            .unwrap();
+
+        assert!(response.headers().get("grpc-status").is_none());
+    }
+}
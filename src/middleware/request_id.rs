@@ -0,0 +1,121 @@
+//! Tags every request with an id, taken from the client or generated, and echoes it back.
+
+use std::task::{Context, Poll};
+
+use hyper::{
+    header::{HeaderName, HeaderValue},
+    Request, Response,
+};
+use tonic::body::BoxBody;
+use tower::{Layer, Service};
+
+use super::BoxFuture;
+
+#[derive(Debug, Clone)]
+pub struct RequestIdLayer {
+    header: HeaderName,
+}
+
+impl RequestIdLayer {
+    /// Reads and echoes the id in `header`, such as `x-request-id`.
+    pub fn new(header: HeaderName) -> Self {
+        Self { header }
+    }
+}
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestId<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestId {
+            inner,
+            header: self.header.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestId<S> {
+    inner: S,
+    header: HeaderName,
+}
+
+impl<S, B> Service<Request<B>> for RequestId<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        let id = match request.headers().get(&self.header) {
+            Some(id) if !id.is_empty() => id.clone(),
+            _ => {
+                let id = HeaderValue::from_str(&format!("{:032x}", rand::random::<u128>()))
+                    .expect("hex digits are a valid header value");
+                // Handlers see the generated id just like one sent by the client.
+                request
+                    .headers_mut()
+                    .insert(self.header.clone(), id.clone());
+                id
+            }
+        };
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let header = self.header.clone();
+        Box::pin(async move {
+            let mut response = inner.call(request).await?;
+            response.headers_mut().insert(header, id);
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn ok(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        Ok(Response::new(tonic::body::empty_body()))
+    }
+
+    fn layer() -> RequestIdLayer {
+        RequestIdLayer::new(HeaderName::from_static("x-correlation-id"))
+    }
+
+    #[tokio::test]
+    async fn echoes_id_from_custom_header() {
+        let service = layer().layer(tower::service_fn(ok));
+        let request = Request::builder()
+            .header("x-correlation-id", "abc-123")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = service.oneshot(request).await.unwrap();
+
+        assert_eq!(response.headers()["x-correlation-id"], "abc-123");
+        assert!(response.headers().get("x-request-id").is_none());
+    }
+
+    #[tokio::test]
+    async fn generates_id_when_missing() {
+        let service = layer().layer(tower::service_fn(ok));
+
+        let response = service.oneshot(Request::new(Body::empty())).await.unwrap();
+
+        assert_eq!(response.headers()["x-correlation-id"].len(), 32);
+    }
+}
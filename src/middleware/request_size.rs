@@ -0,0 +1,351 @@
+//! Records the encoded size of inbound gRPC messages and rejects oversized requests.
+
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use hyper::{
+    body::{Bytes, HttpBody},
+    Body, Request, Response,
+};
+use tokio_stream::StreamExt;
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+use super::BoxFuture;
+use crate::metrics::Metrics;
+
+pub const REQUEST_BYTES_METRIC: &str = "grpc_request_bytes";
+
+/// Length of the gRPC message prefix: a compression flag followed by a big-endian u32 length.
+const FRAME_HEADER_LEN: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct RequestSizeLayer {
+    metrics: Arc<Metrics>,
+    soft_limit: usize,
+}
+
+impl RequestSizeLayer {
+    pub fn new(metrics: Arc<Metrics>, soft_limit: usize) -> Self {
+        Self {
+            metrics,
+            soft_limit,
+        }
+    }
+}
+
+impl<S> Layer<S> for RequestSizeLayer {
+    type Service = RequestSize<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestSize {
+            inner,
+            metrics: self.metrics.clone(),
+            soft_limit: self.soft_limit,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestSize<S> {
+    inner: S,
+    metrics: Arc<Metrics>,
+    soft_limit: usize,
+}
+
+impl<S> Service<Request<Body>> for RequestSize<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let metrics = self.metrics.clone();
+        let soft_limit = self.soft_limit;
+
+        Box::pin(async move {
+            let (parts, mut body) = request.into_parts();
+            let mut frames = FrameSizes::new(metrics, parts.uri.path().to_owned());
+
+            // Buffer until the first frame header is complete, so an oversized unary request
+            // is rejected before it reaches the service.
+            let mut prefix = Vec::new();
+            while frames.count == 0 {
+                match body.data().await {
+                    Some(Ok(chunk)) => {
+                        frames.feed(&chunk);
+                        prefix.extend_from_slice(&chunk);
+                    }
+                    Some(Err(e)) => {
+                        tracing::warn!(error = %e, "failed to read request body");
+                        return Ok(Status::internal("failed to read request body").to_http());
+                    }
+                    None => break,
+                }
+            }
+            if let Err(status) = frames.check(soft_limit) {
+                return Ok(status.to_http());
+            }
+
+            // Later frames are counted as the service reads them; a client stream growing past
+            // the limit fails with the same status mid-stream.
+            let rest = body.map(move |chunk| -> Result<Bytes, BoxError> {
+                let chunk = chunk?;
+                frames.feed(&chunk);
+                frames.check(soft_limit)?;
+                Ok(chunk)
+            });
+            let body = tokio_stream::once(Ok(Bytes::from(prefix))).chain(rest);
+            inner
+                .call(Request::from_parts(parts, Body::wrap_stream(body)))
+                .await
+        })
+    }
+}
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Sums the length prefix of every gRPC frame in a request body as it streams past. The total
+/// is recorded into [`REQUEST_BYTES_METRIC`] when the body is dropped.
+struct FrameSizes {
+    metrics: Arc<Metrics>,
+    method: String,
+    /// Bytes of a frame header split across chunks.
+    header: Vec<u8>,
+    /// Message bytes still to skip before the next frame header.
+    remaining: usize,
+    count: usize,
+    total: usize,
+}
+
+impl FrameSizes {
+    fn new(metrics: Arc<Metrics>, method: String) -> Self {
+        Self {
+            metrics,
+            method,
+            header: Vec::with_capacity(FRAME_HEADER_LEN),
+            remaining: 0,
+            count: 0,
+            total: 0,
+        }
+    }
+
+    fn feed(&mut self, mut chunk: &[u8]) {
+        while !chunk.is_empty() {
+            if self.remaining > 0 {
+                let skipped = self.remaining.min(chunk.len());
+                self.remaining -= skipped;
+                chunk = &chunk[skipped..];
+                continue;
+            }
+            let taken = (FRAME_HEADER_LEN - self.header.len()).min(chunk.len());
+            self.header.extend_from_slice(&chunk[..taken]);
+            chunk = &chunk[taken..];
+            if let Some(size) = frame_length(&self.header) {
+                self.header.clear();
+                self.remaining = size;
+                self.count += 1;
+                self.total += size;
+            }
+        }
+    }
+
+    fn check(&self, soft_limit: usize) -> Result<(), Status> {
+        let size = self.total;
+        if size <= soft_limit {
+            return Ok(());
+        }
+        tracing::warn!(method = %self.method, size, soft_limit, "rejecting oversized request");
+        Err(Status::resource_exhausted(format!(
+            "request of {size} bytes exceeds the limit of {soft_limit} bytes"
+        )))
+    }
+}
+
+impl Drop for FrameSizes {
+    fn drop(&mut self) {
+        if self.count > 0 {
+            self.metrics.record(
+                REQUEST_BYTES_METRIC,
+                &[("method", &self.method)],
+                self.total as f64,
+            );
+        }
+    }
+}
+
+/// Reads the message length from a complete gRPC frame header.
+fn frame_length(header: &[u8]) -> Option<usize> {
+    let length = header.get(1..FRAME_HEADER_LEN)?;
+    Some(u32::from_be_bytes(length.try_into().ok()?) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+    use crate::grpc::posts::Post;
+
+    fn frame(message: &impl Message) -> Vec<u8> {
+        let encoded = message.encode_to_vec();
+        let mut frame = vec![0];
+        frame.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&encoded);
+        frame
+    }
+
+    fn framed(message: &impl Message) -> Body {
+        Body::from(frame(message))
+    }
+
+    /// A client stream of `count` copies of `message`, sent as one chunk per frame, split
+    /// inside the frame header so the counter has to carry it across chunks.
+    fn sync_news_request(message: &impl Message, count: usize) -> Request<Body> {
+        let frame = frame(message);
+        let (head, tail) = frame.split_at(3);
+        let chunks: Vec<_> = (0..count)
+            .flat_map(|_| [head.to_vec(), tail.to_vec()])
+            .map(Ok::<_, std::convert::Infallible>)
+            .collect();
+        let body = tokio_stream::iter(chunks);
+        Request::builder()
+            .uri("/news.NewsService/SyncNews")
+            .body(Body::wrap_stream(body))
+            .unwrap()
+    }
+
+    async fn drain(request: Request<Body>) -> Result<Response<BoxBody>, std::convert::Infallible> {
+        let status = match hyper::body::to_bytes(request.into_body()).await {
+            Ok(_) => Status::ok(""),
+            Err(e) => Status::from_error(e.into()),
+        };
+        Ok(status.to_http())
+    }
+
+    fn create_post_request() -> Request<Body> {
+        let post = Post {
+            user_id: 1,
+            title: "Title".into(),
+            body: "Body".into(),
+            ..Default::default()
+        };
+        Request::builder()
+            .uri("/posts.PostService/CreatePost")
+            .body(framed(&post))
+            .unwrap()
+    }
+
+    async fn ok(_: Request<Body>) -> Result<Response<BoxBody>, std::convert::Infallible> {
+        Ok(Response::new(tonic::body::empty_body()))
+    }
+
+    #[tokio::test]
+    async fn records_request_size_histogram() {
+        let metrics = Arc::new(Metrics::default());
+        let service = RequestSizeLayer::new(metrics.clone(), 1024).layer(tower::service_fn(ok));
+
+        tower::ServiceExt::oneshot(service, create_post_request())
+            .await
+            .unwrap();
+
+        let histogram = metrics
+            .histogram(
+                REQUEST_BYTES_METRIC,
+                &[("method", "/posts.PostService/CreatePost")],
+            )
+            .unwrap();
+        assert_eq!(histogram.count, 1);
+        assert!(histogram.sum > 0.0);
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_above_soft_limit() {
+        let metrics = Arc::new(Metrics::default());
+        let service = RequestSizeLayer::new(metrics, 4).layer(tower::service_fn(ok));
+
+        let response = tower::ServiceExt::oneshot(service, create_post_request())
+            .await
+            .unwrap();
+
+        let code = tonic::Code::from_bytes(response.headers()["grpc-status"].as_bytes());
+        assert_eq!(code, tonic::Code::ResourceExhausted);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_grpc_web_requests() {
+        let metrics = Arc::new(Metrics::default());
+        let service = tower::ServiceBuilder::new()
+            .layer(tonic_web::GrpcWebLayer::new())
+            .layer(RequestSizeLayer::new(metrics, 4))
+            .service(tower::service_fn(ok));
+        let (mut parts, body) = create_post_request().into_parts();
+        parts.headers.insert(
+            hyper::header::CONTENT_TYPE,
+            "application/grpc-web+proto".parse().unwrap(),
+        );
+        parts.method = hyper::Method::POST;
+
+        let response = tower::ServiceExt::oneshot(service, Request::from_parts(parts, body))
+            .await
+            .unwrap();
+
+        let code = tonic::Code::from_bytes(response.headers()["grpc-status"].as_bytes());
+        assert_eq!(code, tonic::Code::ResourceExhausted);
+    }
+
+    #[tokio::test]
+    async fn records_the_sum_of_every_frame_in_a_client_stream() {
+        let metrics = Arc::new(Metrics::default());
+        let post = Post {
+            title: "Title".into(),
+            ..Default::default()
+        };
+        let size = post.encoded_len();
+        let service = RequestSizeLayer::new(metrics.clone(), 1024).layer(tower::service_fn(drain));
+
+        let response = tower::ServiceExt::oneshot(service, sync_news_request(&post, 3))
+            .await
+            .unwrap();
+
+        assert_eq!(response.headers()["grpc-status"], "0");
+        let histogram = metrics
+            .histogram(
+                REQUEST_BYTES_METRIC,
+                &[("method", "/news.NewsService/SyncNews")],
+            )
+            .unwrap();
+        assert_eq!(histogram.count, 1);
+        assert_eq!(histogram.sum, (3 * size) as f64);
+    }
+
+    #[tokio::test]
+    async fn rejects_client_streams_growing_past_soft_limit() {
+        let metrics = Arc::new(Metrics::default());
+        let post = Post {
+            title: "Title".into(),
+            ..Default::default()
+        };
+        // The first frame fits, the second pushes the total over the limit.
+        let limit = post.encoded_len() + 1;
+        let service = RequestSizeLayer::new(metrics, limit).layer(tower::service_fn(drain));
+
+        let response = tower::ServiceExt::oneshot(service, sync_news_request(&post, 2))
+            .await
+            .unwrap();
+
+        let code = tonic::Code::from_bytes(response.headers()["grpc-status"].as_bytes());
+        assert_eq!(code, tonic::Code::ResourceExhausted);
+    }
+}
@@ -0,0 +1,149 @@
+//! Fails RPCs with `deadline_exceeded` once their configured handler timeout elapses.
+//!
+//! Timeouts come from `METHOD_TIMEOUTS`, keyed by full path (`/news.NewsService/GetAllNews`) or
+//! bare method name (`GetAllNews`), falling back to `REQUEST_TIMEOUT_MS`. Only the wait for the
+//! response is bounded, so streaming RPCs are not cut off once they start sending.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use hyper::{Request, Response};
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+use super::BoxFuture;
+
+#[derive(Debug, Clone)]
+pub struct MethodTimeoutLayer {
+    /// Applied to methods without an entry in `per_method`; no timeout when `None`.
+    default: Option<Duration>,
+    per_method: Arc<HashMap<String, Duration>>,
+}
+
+impl MethodTimeoutLayer {
+    pub fn new(default: Option<Duration>, per_method: HashMap<String, Duration>) -> Self {
+        Self {
+            default,
+            per_method: Arc::new(per_method),
+        }
+    }
+
+    fn timeout(&self, path: &str) -> Option<Duration> {
+        let method = path.rsplit('/').next().unwrap_or(path);
+        self.per_method
+            .get(path)
+            .or_else(|| self.per_method.get(method))
+            .copied()
+            .or(self.default)
+    }
+}
+
+impl<S> Layer<S> for MethodTimeoutLayer {
+    type Service = MethodTimeout<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MethodTimeout {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MethodTimeout<S> {
+    inner: S,
+    layer: MethodTimeoutLayer,
+}
+
+impl<S, B> Service<Request<B>> for MethodTimeout<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let path = request.uri().path().to_string();
+        let Some(timeout) = self.layer.timeout(&path) else {
+            return Box::pin(async move { inner.call(request).await });
+        };
+
+        Box::pin(async move {
+            match tokio::time::timeout(timeout, inner.call(request)).await {
+                Ok(result) => result,
+                Err(_) => {
+                    let timeout_ms = timeout.as_millis() as u64;
+                    tracing::warn!(method = path, timeout_ms, "handler timed out");
+                    Ok(Status::deadline_exceeded(format!(
+                        "{path} did not complete within {timeout_ms} ms"
+                    ))
+                    .to_http())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn slow(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Ok(Response::new(tonic::body::empty_body()))
+    }
+
+    async fn status(layer: &MethodTimeoutLayer, path: &str) -> Option<tonic::Code> {
+        let service = layer.layer(tower::service_fn(slow));
+        let request = Request::builder().uri(path).body(Body::empty()).unwrap();
+
+        let response = service.oneshot(request).await.unwrap();
+
+        response
+            .headers()
+            .get("grpc-status")
+            .map(|code| tonic::Code::from_bytes(code.as_bytes()))
+    }
+
+    #[tokio::test]
+    async fn applies_per_method_timeouts_over_the_default() {
+        let layer = MethodTimeoutLayer::new(
+            Some(Duration::from_millis(10)),
+            HashMap::from([
+                (
+                    "/news.NewsService/WatchNews".to_string(),
+                    Duration::from_secs(5),
+                ),
+                ("GetAllNews".to_string(), Duration::from_millis(5)),
+            ]),
+        );
+
+        assert_eq!(status(&layer, "/news.NewsService/WatchNews").await, None);
+        assert_eq!(
+            status(&layer, "/news.NewsService/GetAllNews").await,
+            Some(tonic::Code::DeadlineExceeded)
+        );
+        assert_eq!(
+            status(&layer, "/posts.PostService/ListPosts").await,
+            Some(tonic::Code::DeadlineExceeded)
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Rejects sensitive RPCs that did not arrive over an encrypted connection.
+
+use std::{
+    net::SocketAddr,
+    task::{Context, Poll},
+};
+
+use hyper::{Request, Response};
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+use super::{is_sensitive, BoxFuture};
+
+/// Transport details of the connection a request arrived on, inserted into the request
+/// extensions by the server before any other layer runs.
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionInfo {
+    pub tls: bool,
+    pub peer: Option<SocketAddr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsOnlyLayer {
+    enabled: bool,
+}
+
+impl TlsOnlyLayer {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl<S> Layer<S> for TlsOnlyLayer {
+    type Service = TlsOnly<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TlsOnly {
+            inner,
+            enabled: self.enabled,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsOnly<S> {
+    inner: S,
+    enabled: bool,
+}
+
+impl<S, B> Service<Request<B>> for TlsOnly<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        // Requests without connection info are treated as plaintext.
+        let tls = request
+            .extensions()
+            .get::<ConnectionInfo>()
+            .is_some_and(|info| info.tls);
+        if self.enabled && !tls && is_sensitive(request.uri().path()) {
+            let status = Status::permission_denied("method requires an encrypted connection");
+            return Box::pin(async move { Ok(status.to_http()) });
+        }
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move { inner.call(request).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn ok(_: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        Ok(Response::new(tonic::body::empty_body()))
+    }
+
+    fn request(path: &str, tls: bool) -> Request<Body> {
+        let mut request = Request::builder().uri(path).body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectionInfo { tls, peer: None });
+        request
+    }
+
+    #[tokio::test]
+    async fn rejects_sensitive_methods_over_plaintext() {
+        let service = TlsOnlyLayer::new(true).layer(tower::service_fn(ok));
+
+        let response = service
+            .oneshot(request("/users.UserService/BatchPatchUsers", false))
+            .await
+            .unwrap();
+
+        let code = tonic::Code::from_bytes(response.headers()["grpc-status"].as_bytes());
+        assert_eq!(code, tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn allows_sensitive_methods_over_tls() {
+        let service = TlsOnlyLayer::new(true).layer(tower::service_fn(ok));
+
+        let response = service
+            .oneshot(request("/users.UserService/BatchPatchUsers", true))
+            .await
+            .unwrap();
+
+        assert!(response.headers().get("grpc-status").is_none());
+    }
+
+    #[tokio::test]
+    async fn allows_other_methods_over_plaintext() {
+        let service = TlsOnlyLayer::new(true).layer(tower::service_fn(ok));
+
+        let response = service
+            .oneshot(request("/users.UserService/GetUser", false))
+            .await
+            .unwrap();
+
+        assert!(response.headers().get("grpc-status").is_none());
+    }
+}
@@ -0,0 +1,149 @@
+//! Drops incoming trace context from peers not allowed to continue our traces.
+
+use std::{
+    collections::HashSet,
+    net::IpAddr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use hyper::{Request, Response};
+use tonic::body::BoxBody;
+use tower::{Layer, Service};
+
+use super::{BoxFuture, ConnectionInfo};
+
+/// Headers read by the propagators in `propagation.rs`.
+const TRACE_HEADERS: &[&str] = &[
+    "traceparent",
+    "tracestate",
+    "b3",
+    "x-b3-traceid",
+    "x-b3-spanid",
+    "x-b3-parentspanid",
+    "x-b3-sampled",
+    "x-b3-flags",
+];
+
+/// Strips trace headers from requests whose peer address is not in `trusted`, so their
+/// spans start a fresh trace instead of joining one the client chose. `None` trusts every
+/// peer; requests without connection info are untrusted.
+#[derive(Debug, Clone)]
+pub struct TraceTrustLayer {
+    trusted: Option<Arc<HashSet<IpAddr>>>,
+}
+
+impl TraceTrustLayer {
+    pub fn new(trusted: Option<HashSet<IpAddr>>) -> Self {
+        Self {
+            trusted: trusted.map(Arc::new),
+        }
+    }
+}
+
+impl<S> Layer<S> for TraceTrustLayer {
+    type Service = TraceTrust<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceTrust {
+            inner,
+            trusted: self.trusted.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceTrust<S> {
+    inner: S,
+    trusted: Option<Arc<HashSet<IpAddr>>>,
+}
+
+impl<S, B> Service<Request<B>> for TraceTrust<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        if let Some(trusted) = &self.trusted {
+            let peer = request
+                .extensions()
+                .get::<ConnectionInfo>()
+                .and_then(|info| info.peer);
+            if !peer.is_some_and(|peer| trusted.contains(&peer.ip())) {
+                let headers = request.headers_mut();
+                for name in TRACE_HEADERS {
+                    headers.remove(*name);
+                }
+            }
+        }
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move { inner.call(request).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, net::SocketAddr};
+
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const TRACEPARENT: &str = "00-80f198ee56343ba864fe8b2a57d3eff7-e457b5a2e4d86bd1-01";
+
+    /// Echoes the `traceparent` the inner service received.
+    async fn echo(request: Request<Body>) -> Result<Response<BoxBody>, Infallible> {
+        let mut response = Response::new(tonic::body::empty_body());
+        if let Some(value) = request.headers().get("traceparent") {
+            response.headers_mut().insert("traceparent", value.clone());
+        }
+        Ok(response)
+    }
+
+    fn request(peer: &str) -> Request<Body> {
+        let mut request = Request::builder()
+            .uri("/news.NewsService/GetAllNews")
+            .header("traceparent", TRACEPARENT)
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(ConnectionInfo {
+            tls: false,
+            peer: Some(peer.parse::<SocketAddr>().unwrap()),
+        });
+        request
+    }
+
+    fn layer() -> TraceTrustLayer {
+        TraceTrustLayer::new(Some(HashSet::from(["10.0.0.1".parse().unwrap()])))
+    }
+
+    #[tokio::test]
+    async fn ignores_trace_context_from_untrusted_peers() {
+        let service = layer().layer(tower::service_fn(echo));
+
+        let response = service.oneshot(request("203.0.113.7:5000")).await.unwrap();
+
+        assert!(response.headers().get("traceparent").is_none());
+    }
+
+    #[tokio::test]
+    async fn propagates_trace_context_from_trusted_peers() {
+        let service = layer().layer(tower::service_fn(echo));
+
+        let response = service.oneshot(request("10.0.0.1:5000")).await.unwrap();
+
+        assert_eq!(response.headers()["traceparent"], TRACEPARENT);
+    }
+}
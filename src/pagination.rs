@@ -0,0 +1,77 @@
+//! Cursor-based pagination over the id-ordered in-memory stores.
+
+use tonic::Status;
+
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Opaque token for the next page, empty when this is the last page.
+    pub next_page_token: String,
+}
+
+/// Returns up to `page_size` items following `page_token`.
+///
+/// `items` must be ordered by ascending id, which holds for the stores since ids are assigned
+/// incrementally on insert. The token is the id of the last item of the previous page.
+pub fn paginate<T>(
+    items: impl IntoIterator<Item = T>,
+    id: impl Fn(&T) -> i32,
+    page_token: &str,
+    page_size: usize,
+) -> Result<Page<T>, Status> {
+    paginate_scoped(items, id, "", page_token, page_size)
+}
+
+/// Like [`paginate`], but ties tokens to `scope`, typically the request's filter, so a token
+/// issued for one filter is rejected when replayed with another.
+pub fn paginate_scoped<T>(
+    items: impl IntoIterator<Item = T>,
+    id: impl Fn(&T) -> i32,
+    scope: &str,
+    page_token: &str,
+    page_size: usize,
+) -> Result<Page<T>, Status> {
+    let after = parse_page_token(scope, page_token)?;
+    let mut items: Vec<T> = items
+        .into_iter()
+        .filter(|item| after.is_none_or(|after| id(item) > after))
+        .take(page_size + 1)
+        .collect();
+
+    let next_page_token = if items.len() > page_size {
+        items.truncate(page_size);
+        items
+            .last()
+            .map(|item| format_page_token(scope, id(item)))
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+
+    Ok(Page {
+        items,
+        next_page_token,
+    })
+}
+
+fn format_page_token(scope: &str, id: i32) -> String {
+    if scope.is_empty() {
+        id.to_string()
+    } else {
+        format!("{scope}:{id}")
+    }
+}
+
+fn parse_page_token(scope: &str, token: &str) -> Result<Option<i32>, Status> {
+    if token.is_empty() {
+        return Ok(None);
+    }
+    let (token_scope, id) = token.rsplit_once(':').unwrap_or(("", token));
+    if token_scope != scope {
+        return Err(Status::invalid_argument(
+            "page token does not match the request filter",
+        ));
+    }
+    id.parse()
+        .map(Some)
+        .map_err(|_| Status::invalid_argument("invalid page token"))
+}
@@ -0,0 +1,177 @@
+//! Trace context propagators selected by `OTEL_PROPAGATORS`.
+
+use std::{str::FromStr, sync::OnceLock};
+
+use anyhow::{anyhow, Result};
+use opentelemetry::{
+    propagation::{
+        text_map_propagator::FieldIter, Extractor, Injector, TextMapCompositePropagator,
+        TextMapPropagator,
+    },
+    trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+    Context,
+};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagator {
+    /// W3C `traceparent` / `tracestate`.
+    TraceContext,
+    /// Zipkin B3, injected as the single `b3` header.
+    B3,
+}
+
+impl FromStr for Propagator {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "tracecontext" => Ok(Self::TraceContext),
+            "b3" => Ok(Self::B3),
+            other => Err(format!("unknown propagator `{other}`")),
+        }
+    }
+}
+
+/// Parses a comma-separated propagator list such as `tracecontext,b3`, ignoring duplicates.
+pub fn parse_propagators(value: &str) -> Result<Vec<Propagator>> {
+    let mut propagators = Vec::new();
+    for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let propagator = name
+            .parse()
+            .map_err(|e| anyhow!("invalid value for OTEL_PROPAGATORS: {e}"))?;
+        if !propagators.contains(&propagator) {
+            propagators.push(propagator);
+        }
+    }
+    if propagators.is_empty() {
+        return Err(anyhow!(
+            "OTEL_PROPAGATORS must name at least one propagator"
+        ));
+    }
+    Ok(propagators)
+}
+
+/// Builds one propagator that injects and extracts every format in `propagators`.
+pub fn composite(propagators: &[Propagator]) -> TextMapCompositePropagator {
+    TextMapCompositePropagator::new(
+        propagators
+            .iter()
+            .map(|propagator| -> Box<dyn TextMapPropagator + Send + Sync> {
+                match propagator {
+                    Propagator::TraceContext => Box::new(TraceContextPropagator::new()),
+                    Propagator::B3 => Box::new(B3Propagator),
+                }
+            })
+            .collect(),
+    )
+}
+
+const B3_SINGLE_HEADER: &str = "b3";
+const B3_TRACE_ID_HEADER: &str = "x-b3-traceid";
+const B3_SPAN_ID_HEADER: &str = "x-b3-spanid";
+const B3_SAMPLED_HEADER: &str = "x-b3-sampled";
+const B3_FLAGS_HEADER: &str = "x-b3-flags";
+
+/// Zipkin B3 propagation. Injects the single `b3` header and extracts either the single or
+/// the multi-header (`X-B3-*`) form.
+#[derive(Debug)]
+pub struct B3Propagator;
+
+impl B3Propagator {
+    fn extract_span_context(extractor: &dyn Extractor) -> Option<SpanContext> {
+        let (trace_id, span_id, sampled) = match extractor.get(B3_SINGLE_HEADER) {
+            Some(header) => {
+                let mut parts = header.split('-');
+                let trace_id = parts.next()?;
+                let span_id = parts.next()?;
+                (trace_id, span_id, parts.next())
+            }
+            None => (
+                extractor.get(B3_TRACE_ID_HEADER)?,
+                extractor.get(B3_SPAN_ID_HEADER)?,
+                extractor
+                    .get(B3_SAMPLED_HEADER)
+                    .or(extractor.get(B3_FLAGS_HEADER)),
+            ),
+        };
+
+        // 64-bit trace ids are left-padded to 128 bits.
+        if !matches!(trace_id.len(), 16 | 32) || span_id.len() != 16 {
+            return None;
+        }
+        let trace_id = TraceId::from_hex(&format!("{trace_id:0>32}")).ok()?;
+        let span_id = SpanId::from_hex(span_id).ok()?;
+        // "d" (single header) and `X-B3-Flags: 1` mean debug, which implies sampled.
+        let flags = match sampled {
+            Some("1" | "true" | "d") => TraceFlags::SAMPLED,
+            _ => TraceFlags::default(),
+        };
+        let span_context = SpanContext::new(trace_id, span_id, flags, true, TraceState::default());
+        span_context.is_valid().then_some(span_context)
+    }
+}
+
+impl TextMapPropagator for B3Propagator {
+    fn inject_context(&self, cx: &Context, injector: &mut dyn Injector) {
+        let span = cx.span();
+        let span_context = span.span_context();
+        if !span_context.is_valid() {
+            return;
+        }
+        let sampled = if span_context.is_sampled() { "1" } else { "0" };
+        injector.set(
+            B3_SINGLE_HEADER,
+            format!(
+                "{:032x}-{:016x}-{sampled}",
+                span_context.trace_id(),
+                span_context.span_id()
+            ),
+        );
+    }
+
+    fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
+        match Self::extract_span_context(extractor) {
+            Some(span_context) => cx.with_remote_span_context(span_context),
+            None => cx.clone(),
+        }
+    }
+
+    fn fields(&self) -> FieldIter<'_> {
+        static FIELDS: OnceLock<[String; 1]> = OnceLock::new();
+        FieldIter::new(FIELDS.get_or_init(|| [B3_SINGLE_HEADER.to_string()]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn parses_propagator_lists() {
+        assert_eq!(
+            parse_propagators("tracecontext, b3").unwrap(),
+            vec![Propagator::TraceContext, Propagator::B3]
+        );
+        assert_eq!(parse_propagators("b3,b3").unwrap(), vec![Propagator::B3]);
+        assert!(parse_propagators("jaeger").is_err());
+        assert!(parse_propagators(" , ").is_err());
+    }
+
+    #[test]
+    fn b3_round_trips_span_context() {
+        let mut headers = HashMap::new();
+        headers.insert(
+            "b3".to_string(),
+            "80f198ee56343ba864fe8b2a57d3eff7-e457b5a2e4d86bd1-1".to_string(),
+        );
+
+        let cx = B3Propagator.extract(&headers);
+        let mut injected = HashMap::new();
+        B3Propagator.inject_context(&cx, &mut injected);
+
+        assert_eq!(injected, headers);
+    }
+}
@@ -0,0 +1,72 @@
+//! Fixed-window request limits per caller.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Callers tracked before expired windows are swept.
+const SWEEP_THRESHOLD: usize = 1024;
+
+/// Allows each key at most `limit` calls per `window`.
+#[derive(Debug)]
+pub struct RateLimiter<K> {
+    limit: u32,
+    window: Duration,
+    /// Start of each key's current window and the calls made in it.
+    windows: Mutex<HashMap<K, (Instant, u32)>>,
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            windows: Mutex::default(),
+        }
+    }
+
+    /// Counts a call by `key`, returning whether it is within the limit.
+    pub fn check(&self, key: K) -> bool {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() >= SWEEP_THRESHOLD {
+            windows.retain(|_, (started_at, _)| now.duration_since(*started_at) < self.window);
+        }
+        let (started_at, calls) = windows.entry(key).or_insert((now, 0));
+        if now.duration_since(*started_at) >= self.window {
+            *started_at = now;
+            *calls = 0;
+        }
+        if *calls >= self.limit {
+            return false;
+        }
+        *calls += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_each_key_separately() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+
+        assert!(limiter.check("a"));
+        assert!(limiter.check("a"));
+        assert!(!limiter.check("a"));
+        assert!(limiter.check("b"));
+    }
+
+    #[test]
+    fn resets_after_the_window() {
+        let limiter = RateLimiter::new(1, Duration::ZERO);
+
+        assert!(limiter.check("a"));
+        assert!(limiter.check("a"));
+    }
+}
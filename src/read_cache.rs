@@ -0,0 +1,143 @@
+//! Short-lived cache of encoded read responses, keyed by method and encoded request.
+//!
+//! Entries record the entity type they were read from and are dropped as soon as any entity of
+//! that type changes, so the TTL only bounds how long an unchanged store is served from memory.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::grpc::events::EntityType;
+
+#[derive(Debug, Clone)]
+pub struct ReadCache {
+    /// `None` disables caching.
+    ttl: Option<Duration>,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    entries: HashMap<(&'static str, Vec<u8>), Entry>,
+    /// Bumped on every invalidation, so a read that raced a mutation is not cached.
+    generations: HashMap<EntityType, u64>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    entity_type: EntityType,
+    stored_at: Instant,
+    response: Vec<u8>,
+}
+
+impl ReadCache {
+    pub fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            ttl,
+            state: Arc::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.ttl.is_some()
+    }
+
+    /// The cached response to `method` for `request`, unless missing or expired.
+    pub fn get(&self, method: &'static str, request: &[u8]) -> Option<Vec<u8>> {
+        let ttl = self.ttl?;
+        let mut state = self.state.lock().unwrap();
+        let key = (method, request.to_vec());
+        match state.entries.get(&key) {
+            Some(entry) if entry.stored_at.elapsed() < ttl => Some(entry.response.clone()),
+            Some(_) => {
+                state.entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Current generation of `entity_type`, to be taken before reading the store and passed
+    /// to [`ReadCache::insert`].
+    pub fn generation(&self, entity_type: EntityType) -> u64 {
+        let state = self.state.lock().unwrap();
+        state.generations.get(&entity_type).copied().unwrap_or(0)
+    }
+
+    /// Caches `response`, unless `entity_type` was invalidated since `generation` was taken.
+    pub fn insert(
+        &self,
+        method: &'static str,
+        request: Vec<u8>,
+        entity_type: EntityType,
+        generation: u64,
+        response: Vec<u8>,
+    ) {
+        if self.ttl.is_none() {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.generations.get(&entity_type).copied().unwrap_or(0) != generation {
+            return;
+        }
+        state.entries.insert(
+            (method, request),
+            Entry {
+                entity_type,
+                stored_at: Instant::now(),
+                response,
+            },
+        );
+    }
+
+    /// Drops every response read from `entity_type`.
+    pub fn invalidate(&self, entity_type: EntityType) {
+        let mut state = self.state.lock().unwrap();
+        *state.generations.entry(entity_type).or_insert(0) += 1;
+        state
+            .entries
+            .retain(|_, entry| entry.entity_type != entity_type);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METHOD: &str = "/news.NewsService/GetAllNews";
+
+    #[test]
+    fn serves_fresh_entries_until_invalidated() {
+        let cache = ReadCache::new(Some(Duration::from_secs(60)));
+        let generation = cache.generation(EntityType::News);
+        cache.insert(METHOD, vec![1], EntityType::News, generation, vec![42]);
+
+        assert_eq!(cache.get(METHOD, &[1]), Some(vec![42]));
+        assert_eq!(cache.get(METHOD, &[2]), None);
+
+        cache.invalidate(EntityType::Post);
+        assert_eq!(cache.get(METHOD, &[1]), Some(vec![42]));
+        cache.invalidate(EntityType::News);
+        assert_eq!(cache.get(METHOD, &[1]), None);
+    }
+
+    #[test]
+    fn skips_reads_that_raced_an_invalidation() {
+        let cache = ReadCache::new(Some(Duration::from_secs(60)));
+        let generation = cache.generation(EntityType::News);
+        cache.invalidate(EntityType::News);
+        cache.insert(METHOD, vec![], EntityType::News, generation, vec![42]);
+
+        assert_eq!(cache.get(METHOD, &[]), None);
+    }
+
+    #[test]
+    fn expires_entries_after_ttl() {
+        let cache = ReadCache::new(Some(Duration::ZERO));
+        cache.insert(METHOD, vec![], EntityType::News, 0, vec![42]);
+
+        assert_eq!(cache.get(METHOD, &[]), None);
+    }
+}
@@ -0,0 +1,48 @@
+//! Masking of personal data before it is written to logs or shown to unauthenticated callers.
+
+/// Masks the local part of an email address, keeping its first character and the domain.
+pub fn email(email: &str) -> String {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            let first = local.chars().next().map(String::from).unwrap_or_default();
+            format!("{first}***@{domain}")
+        }
+        None => "***".into(),
+    }
+}
+
+/// Masks every digit of a phone number except the last four, preserving separators.
+pub fn phone(phone: &str) -> String {
+    let digits = phone.chars().filter(char::is_ascii_digit).count();
+    let mut seen = 0;
+    phone
+        .chars()
+        .map(|c| {
+            if !c.is_ascii_digit() {
+                return c;
+            }
+            seen += 1;
+            if seen + 4 > digits {
+                c
+            } else {
+                '*'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn email_keeps_first_char_and_domain() {
+        assert_eq!(email("sincere@april.biz"), "s***@april.biz");
+        assert_eq!(email("not-an-email"), "***");
+    }
+
+    #[test]
+    fn phone_keeps_last_four_digits() {
+        assert_eq!(phone("770-736-8031"), "***-***-8031");
+    }
+}
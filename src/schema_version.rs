@@ -0,0 +1,71 @@
+//! Negotiation of the request schema version declared in `x-schema-version`, so handlers can
+//! fill in fields that older clients do not know about.
+
+use tonic::{metadata::MetadataMap, Status};
+
+use crate::grpc::news::News;
+
+pub const SCHEMA_VERSION_HEADER: &str = "x-schema-version";
+
+/// Newest schema version this server understands.
+pub const CURRENT: u32 = 2;
+
+/// Language given to news created by clients older than schema version 2, which added it.
+pub const DEFAULT_NEWS_LANGUAGE: &str = "en";
+
+/// Reads the declared schema version, falling back to `default` when the header is absent.
+/// Versions that do not parse, or are outside `1..=CURRENT`, are rejected.
+pub fn negotiate(metadata: &MetadataMap, default: u32) -> Result<u32, Status> {
+    let Some(value) = metadata.get(SCHEMA_VERSION_HEADER) else {
+        return Ok(default);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .filter(|version| (1..=CURRENT).contains(version))
+        .ok_or_else(|| {
+            Status::invalid_argument(format!(
+                "{SCHEMA_VERSION_HEADER} must be a version between 1 and {CURRENT}"
+            ))
+        })
+}
+
+/// Applies the defaults for fields added after `version` to a news item being created.
+/// Fields a client at `version` must set itself, like `language` from version 2, are left
+/// empty for validation to report.
+pub fn default_news_fields(news: &mut News, version: u32) {
+    if news.language.is_empty() && version < 2 {
+        news.language = DEFAULT_NEWS_LANGUAGE.into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(version: &str) -> MetadataMap {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(SCHEMA_VERSION_HEADER, version.parse().unwrap());
+        metadata
+    }
+
+    #[test]
+    fn negotiates_declared_or_default_version() {
+        assert_eq!(negotiate(&MetadataMap::new(), 1).unwrap(), 1);
+        assert_eq!(negotiate(&metadata("2"), 1).unwrap(), 2);
+        assert!(negotiate(&metadata("3"), 1).is_err());
+        assert!(negotiate(&metadata("latest"), 1).is_err());
+    }
+
+    #[test]
+    fn defaults_language_only_for_v1() {
+        let mut news = News::default();
+        default_news_fields(&mut news, 1);
+        assert_eq!(news.language, DEFAULT_NEWS_LANGUAGE);
+
+        let mut news = News::default();
+        default_news_fields(&mut news, 2);
+        assert!(news.language.is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+//! Startup checks that the compiled-in descriptor set matches the services this binary serves.
+
+use prost::Message;
+use prost_types::FileDescriptorSet;
+
+/// Fully qualified names of the services registered in `bind()`.
+pub const EXPECTED_SERVICES: &[&str] =
+    &["news.NewsService", "posts.PostService", "users.UserService"];
+
+/// Decodes `descriptor_set` and fails unless it declares every service in [`EXPECTED_SERVICES`].
+pub fn check_descriptor_set(descriptor_set: &[u8]) -> Result<(), String> {
+    let set = FileDescriptorSet::decode(descriptor_set)
+        .map_err(|e| format!("file descriptor set does not decode: {e}"))?;
+    let services: Vec<String> = set
+        .file
+        .iter()
+        .flat_map(|file| {
+            file.service
+                .iter()
+                .map(move |service| format!("{}.{}", file.package(), service.name()))
+        })
+        .collect();
+    let missing: Vec<&str> = EXPECTED_SERVICES
+        .iter()
+        .copied()
+        .filter(|expected| !services.iter().any(|service| service == expected))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "file descriptor set is missing services: {}",
+            missing.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Fully qualified name and method names of every service declared in `descriptor_set`.
+pub fn service_methods(descriptor_set: &[u8]) -> Result<Vec<(String, Vec<String>)>, String> {
+    let set = FileDescriptorSet::decode(descriptor_set)
+        .map_err(|e| format!("file descriptor set does not decode: {e}"))?;
+    Ok(set
+        .file
+        .iter()
+        .flat_map(|file| {
+            file.service.iter().map(move |service| {
+                let methods = service
+                    .method
+                    .iter()
+                    .map(|method| method.name().to_string())
+                    .collect();
+                (format!("{}.{}", file.package(), service.name()), methods)
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::FILE_DESCRIPTOR_SET;
+
+    #[test]
+    fn descriptor_set_contains_expected_services() {
+        assert_eq!(check_descriptor_set(FILE_DESCRIPTOR_SET), Ok(()));
+    }
+
+    #[test]
+    fn rejects_descriptor_set_without_services() {
+        let empty = FileDescriptorSet::default().encode_to_vec();
+
+        let error = check_descriptor_set(&empty).unwrap_err();
+
+        assert!(error.contains("news.NewsService"));
+    }
+
+    #[test]
+    fn lists_methods_per_service() {
+        let services = service_methods(FILE_DESCRIPTOR_SET).unwrap();
+
+        let (_, methods) = services
+            .iter()
+            .find(|(service, _)| service == "news.NewsService")
+            .unwrap();
+        assert!(methods.iter().any(|m| m == "GetNews"));
+        assert!(methods.iter().any(|m| m == "AddNews"));
+    }
+}
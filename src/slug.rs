@@ -0,0 +1,70 @@
+//! URL slugs derived from titles.
+
+/// Lowercases `title` and joins its ASCII alphanumeric runs with single hyphens.
+pub fn slugify(title: &str) -> String {
+    let words: Vec<String> = title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if words.is_empty() {
+        // Titles without any ASCII letters or digits still need a usable slug.
+        return "news".into();
+    }
+    words.join("-")
+}
+
+/// Returns `base`, or `base-2`, `base-3`, ... for the first candidate that is not `taken`.
+pub fn unique(base: String, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| format!("{base}-{n}"))
+        .find(|candidate| !taken(candidate))
+        .unwrap()
+}
+
+/// Whether `slug` is `base` itself or `base` with a numeric suffix from [`unique`].
+pub fn is_derived(slug: &str, base: &str) -> bool {
+    match slug.strip_prefix(base) {
+        Some("") => true,
+        Some(rest) => rest
+            .strip_prefix('-')
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugifies_titles() {
+        assert_eq!(slugify("Hello World"), "hello-world");
+        assert_eq!(
+            slugify("  Rust 1.80: what's new?! "),
+            "rust-1-80-what-s-new"
+        );
+        assert_eq!(slugify("¡¿?!"), "news");
+    }
+
+    #[test]
+    fn appends_numeric_suffix_on_collision() {
+        let taken = ["hello-world", "hello-world-2"];
+        assert_eq!(
+            unique("hello-world".into(), |s| taken.contains(&s)),
+            "hello-world-3"
+        );
+        assert_eq!(unique("other".into(), |s| taken.contains(&s)), "other");
+    }
+
+    #[test]
+    fn recognizes_derived_slugs() {
+        assert!(is_derived("hello-world", "hello-world"));
+        assert!(is_derived("hello-world-3", "hello-world"));
+        assert!(!is_derived("hello-world-again", "hello-world"));
+        assert!(!is_derived("hello", "hello-world"));
+    }
+}
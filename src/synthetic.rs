@@ -0,0 +1,117 @@
+//! Synthetic records for load testing, used in place of the built-in fixtures when
+//! `SEED_COUNT` is set.
+//!
+//! Generation is driven by a fixed RNG seed, so every run with the same count produces the
+//! same data and results stay comparable.
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    grpc::{
+        news::{News, Status as NewsStatus},
+        posts::Post,
+        users::User,
+    },
+    slug, SEED_TIMESTAMP,
+};
+
+/// Seed of the generator's RNG.
+const SEED: u64 = 0x5eed;
+
+/// Creation times are spread over the year after `SEED_TIMESTAMP`.
+const SPREAD_MS: i64 = 365 * 24 * 60 * 60 * 1000;
+
+const WORDS: &[&str] = &[
+    "apple", "bridge", "cloud", "delta", "ember", "forest", "garden", "harbor", "island", "jungle",
+    "kernel", "lantern", "meadow", "nebula", "orbit", "prairie", "quartz", "river", "summit",
+    "tundra", "upland", "valley", "willow", "zenith",
+];
+
+#[derive(Debug, Default)]
+pub struct Dataset {
+    pub news: Vec<News>,
+    pub posts: Vec<Post>,
+    pub users: Vec<User>,
+}
+
+/// Generates `count` users, posts and news items with ids `1..=count`. Every record passes
+/// validation; posts and news are attributed to generated users.
+pub fn generate(count: usize) -> Dataset {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let mut dataset = Dataset::default();
+    for id in 1..=count as i32 {
+        let created_at = SEED_TIMESTAMP + rng.gen_range(0..SPREAD_MS);
+        dataset.users.push(User {
+            id,
+            name: format!("User {id}"),
+            username: format!("user{id}"),
+            // The id keeps emails unique.
+            email: format!("user{id}@example.com"),
+            created_at,
+            updated_at: created_at,
+            ..Default::default()
+        });
+    }
+    for id in 1..=count as i32 {
+        let created_at = SEED_TIMESTAMP + rng.gen_range(0..SPREAD_MS);
+        dataset.posts.push(Post {
+            id,
+            user_id: rng.gen_range(1..=count as i32),
+            title: phrase(&mut rng, 4),
+            body: phrase(&mut rng, 30),
+            created_at,
+            updated_at: created_at,
+            ..Default::default()
+        });
+    }
+    for id in 1..=count as i32 {
+        let created_at = SEED_TIMESTAMP + rng.gen_range(0..SPREAD_MS);
+        let status = *[NewsStatus::Published, NewsStatus::Draft]
+            .choose(&mut rng)
+            .unwrap();
+        // The id keeps titles, and so slugs, unique.
+        let title = format!("{} {id}", phrase(&mut rng, 3));
+        dataset.news.push(News {
+            id,
+            slug: slug::slugify(&title),
+            title,
+            body: phrase(&mut rng, 40),
+            status: status.into(),
+            author_id: Some(rng.gen_range(1..=count as i32)),
+            created_at,
+            updated_at: created_at,
+            ..Default::default()
+        });
+    }
+    dataset
+}
+
+/// `words` random words, the first capitalized.
+fn phrase(rng: &mut StdRng, words: usize) -> String {
+    let mut phrase = (0..words)
+        .map(|_| *WORDS.choose(rng).unwrap())
+        .collect::<Vec<_>>()
+        .join(" ");
+    phrase[..1].make_ascii_uppercase();
+    phrase
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::validation::{validate_news, validate_user};
+
+    use super::*;
+
+    #[test]
+    fn generates_valid_records_deterministically() {
+        let dataset = generate(20);
+
+        assert!(dataset.users.iter().all(|u| validate_user(u).is_ok()));
+        assert!(dataset.posts.iter().all(|p| !p.title.trim().is_empty()));
+        assert!(dataset
+            .news
+            .iter()
+            .all(|n| validate_news(n, None, false).is_ok()));
+        assert_eq!(generate(20).posts, dataset.posts);
+    }
+}
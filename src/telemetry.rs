@@ -0,0 +1,183 @@
+//! Span exporter selection via `OTEL_TRACES_EXPORTER`.
+
+use std::{future::Future, pin::Pin, time::SystemTime};
+
+use anyhow::{anyhow, Result};
+use opentelemetry::{
+    trace::{Link, SamplingDecision, SamplingResult, SpanKind, TraceContextExt, TraceId},
+    Context, KeyValue, Value,
+};
+use opentelemetry_sdk::{
+    export::trace::{ExportResult, SpanData, SpanExporter},
+    trace::{Sampler, ShouldSample},
+};
+
+/// Span attribute that makes [`ErrorAwareSampler`] keep a span regardless of the base ratio.
+pub const FORCE_SAMPLE_ATTRIBUTE: &str = "sampling.force";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TracesExporter {
+    /// Ships spans to Honeycomb over OTLP; requires `HONEYCOMB_API_KEY`.
+    Otlp,
+    /// Prints one line per finished span, for local development.
+    Stdout,
+    /// Tracing is disabled.
+    None,
+}
+
+/// Parses `OTEL_TRACES_EXPORTER`, defaulting to OTLP when unset.
+pub fn traces_exporter(value: Option<&str>) -> Result<TracesExporter> {
+    match value.map(str::trim) {
+        None | Some("otlp") => Ok(TracesExporter::Otlp),
+        Some("stdout") => Ok(TracesExporter::Stdout),
+        Some("none") => Ok(TracesExporter::None),
+        Some(other) => Err(anyhow!(
+            "invalid value for OTEL_TRACES_EXPORTER: expected otlp, stdout or none, got `{other}`"
+        )),
+    }
+}
+
+/// Parses `OTEL_TRACES_SAMPLER_ARG`, the fraction of traces to sample, defaulting to all.
+pub fn sample_ratio(value: Option<&str>) -> Result<f64> {
+    let Some(value) = value else {
+        return Ok(1.0);
+    };
+    match value.trim().parse::<f64>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err(anyhow!(
+            "invalid value for OTEL_TRACES_SAMPLER_ARG: expected a number between 0 and 1"
+        )),
+    }
+}
+
+/// Samples like `base`, except that spans created with [`FORCE_SAMPLE_ATTRIBUTE`] set to
+/// `true` are always recorded and sampled, so failures are traced even at low ratios.
+#[derive(Debug, Clone)]
+pub struct ErrorAwareSampler {
+    base: Sampler,
+}
+
+impl ErrorAwareSampler {
+    pub fn new(base: Sampler) -> Self {
+        Self { base }
+    }
+}
+
+impl ShouldSample for ErrorAwareSampler {
+    fn should_sample(
+        &self,
+        parent_context: Option<&Context>,
+        trace_id: TraceId,
+        name: &str,
+        span_kind: &SpanKind,
+        attributes: &[KeyValue],
+        links: &[Link],
+    ) -> SamplingResult {
+        let forced = attributes
+            .iter()
+            .any(|kv| kv.key.as_str() == FORCE_SAMPLE_ATTRIBUTE && kv.value == Value::Bool(true));
+        if !forced {
+            return self.base.should_sample(
+                parent_context,
+                trace_id,
+                name,
+                span_kind,
+                attributes,
+                links,
+            );
+        }
+        SamplingResult {
+            decision: SamplingDecision::RecordAndSample,
+            attributes: Vec::new(),
+            trace_state: parent_context
+                .map(|cx| cx.span().span_context().trace_state().clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Writes each exported span to stdout as a single human-readable line.
+#[derive(Debug)]
+pub struct StdoutExporter;
+
+impl SpanExporter for StdoutExporter {
+    fn export(
+        &mut self,
+        batch: Vec<SpanData>,
+    ) -> Pin<Box<dyn Future<Output = ExportResult> + Send + 'static>> {
+        for span in batch {
+            let duration = span
+                .end_time
+                .duration_since(span.start_time)
+                .unwrap_or_default();
+            let start = span
+                .start_time
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default();
+            let attributes: Vec<String> = span
+                .attributes
+                .iter()
+                .map(|kv| format!("{}={}", kv.key.as_str(), kv.value.as_str()))
+                .collect();
+            println!(
+                "span name={} trace_id={:032x} span_id={:016x} parent_id={:016x} start_ms={} duration_ms={:.3} {}",
+                span.name,
+                span.span_context.trace_id(),
+                span.span_context.span_id(),
+                span.parent_span_id,
+                start.as_millis(),
+                duration.as_secs_f64() * 1000.0,
+                attributes.join(" "),
+            );
+        }
+        Box::pin(std::future::ready(Ok(())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_exporter_from_env_value() {
+        assert_eq!(traces_exporter(None).unwrap(), TracesExporter::Otlp);
+        assert_eq!(traces_exporter(Some("otlp")).unwrap(), TracesExporter::Otlp);
+        assert_eq!(
+            traces_exporter(Some("stdout")).unwrap(),
+            TracesExporter::Stdout
+        );
+        assert_eq!(traces_exporter(Some("none")).unwrap(), TracesExporter::None);
+        assert!(traces_exporter(Some("jaeger")).is_err());
+    }
+
+    #[test]
+    fn parses_sample_ratio() {
+        assert_eq!(sample_ratio(None).unwrap(), 1.0);
+        assert_eq!(sample_ratio(Some("0.25")).unwrap(), 0.25);
+        assert!(sample_ratio(Some("2")).is_err());
+        assert!(sample_ratio(Some("half")).is_err());
+    }
+
+    #[test]
+    fn forced_spans_are_sampled_even_when_base_drops_everything() {
+        let sampler = ErrorAwareSampler::new(Sampler::AlwaysOff);
+        let sample = |attributes: &[KeyValue]| {
+            sampler
+                .should_sample(
+                    None,
+                    TraceId::from_u128(1),
+                    "grpc.error",
+                    &SpanKind::Internal,
+                    attributes,
+                    &[],
+                )
+                .decision
+        };
+
+        assert_eq!(
+            sample(&[KeyValue::new(FORCE_SAMPLE_ATTRIBUTE, true)]),
+            SamplingDecision::RecordAndSample
+        );
+        assert_eq!(sample(&[]), SamplingDecision::Drop);
+    }
+}
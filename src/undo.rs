@@ -0,0 +1,142 @@
+//! Before-states of recent mutations, for reverting them with `UndoLastChange`.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use crate::grpc::{
+    events::ChangeType,
+    news::News,
+    posts::{Post, PostRevision},
+    users::User,
+};
+
+/// State of an entity before a mutation, `None` when the mutation created it. News and posts
+/// also keep their edit history as it was, `None` when there was none.
+#[derive(Debug, Clone)]
+pub enum Before {
+    News {
+        id: i32,
+        item: Option<News>,
+        history: Option<VecDeque<News>>,
+    },
+    Post {
+        id: i32,
+        item: Option<Post>,
+        revisions: Option<VecDeque<PostRevision>>,
+    },
+    User(i32, Option<User>),
+}
+
+/// Before-states of every entity changed by one RPC, in the order they were changed.
+#[derive(Debug, Clone)]
+pub struct UndoEntry {
+    /// Change-feed sequence of the RPC's last change event.
+    pub sequence: u64,
+    pub before: Vec<Before>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UndoLog {
+    /// Oldest first.
+    entries: Arc<Mutex<VecDeque<UndoEntry>>>,
+    capacity: usize,
+}
+
+impl UndoLog {
+    /// Creates a log keeping the before-states of the last `capacity` mutating RPCs.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Arc::default(),
+            capacity,
+        }
+    }
+
+    /// Records one RPC's before-states under the sequence of its last change event. RPCs that
+    /// changed nothing are not recorded.
+    ///
+    /// Must be called while holding the locks of the mutated stores, so entries follow mutation
+    /// order.
+    pub fn record(&self, sequence: u64, before: Vec<Before>) {
+        if before.is_empty() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_back(UndoEntry { sequence, before });
+        if entries.len() > self.capacity {
+            entries.pop_front();
+        }
+    }
+
+    /// Removes and returns the most recent entry.
+    pub fn pop(&self) -> Option<UndoEntry> {
+        self.entries.lock().unwrap().pop_back()
+    }
+}
+
+/// Reverts entity `id` in `store` to `before`, keeping the store ordered by id.
+///
+/// Returns the change this amounts to, with the entity's restored state or, for a deletion, the
+/// removed entity.
+pub fn restore<T>(
+    store: &mut Vec<T>,
+    id: i32,
+    before: Option<T>,
+    key: impl Fn(&T) -> i32,
+) -> (ChangeType, Option<T>)
+where
+    T: Clone,
+{
+    let index = store.iter().position(|entity| key(entity) == id);
+    match (before, index) {
+        (None, Some(index)) => (ChangeType::Deleted, Some(store.remove(index))),
+        (None, None) => (ChangeType::Deleted, None),
+        (Some(before), Some(index)) => {
+            store[index] = before.clone();
+            (ChangeType::Updated, Some(before))
+        }
+        (Some(before), None) => {
+            let at = store.partition_point(|entity| key(entity) < id);
+            store.insert(at, before.clone());
+            (ChangeType::Created, Some(before))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_reinserts_deleted_entity_in_id_order() {
+        let mut store = vec![1, 3];
+
+        let (change_type, restored) = restore(&mut store, 2, Some(2), |&n| n);
+
+        assert_eq!(change_type, ChangeType::Created);
+        assert_eq!(restored, Some(2));
+        assert_eq!(store, [1, 2, 3]);
+    }
+
+    #[test]
+    fn log_drops_oldest_entries_beyond_capacity() {
+        let log = UndoLog::with_capacity(1);
+        log.record(1, vec![Before::User(1, None)]);
+        log.record(2, vec![Before::User(2, None), Before::User(3, None)]);
+
+        let entry = log.pop().unwrap();
+        assert_eq!(entry.sequence, 2);
+        assert_eq!(entry.before.len(), 2);
+        assert!(log.pop().is_none());
+    }
+
+    #[test]
+    fn log_skips_rpcs_without_changes() {
+        let log = UndoLog::with_capacity(1);
+        log.record(1, vec![Before::User(1, None)]);
+        log.record(2, Vec::new());
+
+        assert_eq!(log.pop().unwrap().sequence, 1);
+    }
+}
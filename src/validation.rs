@@ -0,0 +1,113 @@
+//! Payload validators run by the create/edit handlers before anything is stored.
+
+use tonic::{Code, Status};
+use tonic_types::{ErrorDetails, StatusExt};
+
+use crate::grpc::{news::News, users::User};
+
+pub fn validate_news(
+    news: &News,
+    body_limit: Option<usize>,
+    require_language: bool,
+) -> Result<(), Status> {
+    news_violations(news, body_limit, require_language).into_result()
+}
+
+/// Every check `news` fails, as `(field, description)` pairs; empty when it is valid.
+/// `body_limit` is the create or update limit in bytes, and `require_language` is set once
+/// schema defaults have been applied to a new item, so only v2+ clients can still lack it.
+pub fn news_violations(
+    news: &News,
+    body_limit: Option<usize>,
+    require_language: bool,
+) -> Violations {
+    let mut violations = Violations::default();
+    violations.check(
+        !news.title.trim().is_empty(),
+        "title",
+        "title must not be empty",
+    );
+    violations.check(
+        !require_language || !news.language.is_empty(),
+        "language",
+        "language is required from schema version 2",
+    );
+    if let Some(limit) = body_limit.filter(|limit| news.body.len() > *limit) {
+        violations.check(
+            false,
+            "body",
+            format!(
+                "body is {} bytes; at most {limit} are allowed",
+                news.body.len()
+            ),
+        );
+    }
+    violations
+}
+
+pub fn validate_user(user: &User) -> Result<(), Status> {
+    let mut violations = Violations::default();
+    violations.check(
+        !user.name.trim().is_empty(),
+        "name",
+        "name must not be empty",
+    );
+    violations.check(
+        is_valid_email(&user.email),
+        "email",
+        "email is not a valid address",
+    );
+    violations.into_result()
+}
+
+/// Every failed check of one payload, so clients can flag all bad fields at once.
+#[derive(Default)]
+pub struct Violations(Vec<(&'static str, String)>);
+
+impl Violations {
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &str)> + '_ {
+        self.0
+            .iter()
+            .map(|(field, description)| (*field, description.as_str()))
+    }
+
+    fn check(&mut self, ok: bool, field: &'static str, description: impl Into<String>) {
+        if !ok {
+            self.0.push((field, description.into()));
+        }
+    }
+
+    /// `INVALID_ARGUMENT` listing each violation in its `BadRequest` details.
+    pub fn into_result(self) -> Result<(), Status> {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        let message = self
+            .0
+            .iter()
+            .map(|(_, description)| description.as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+        let mut details = ErrorDetails::new();
+        for (field, description) in &self.0 {
+            details.add_bad_request_violation(*field, description);
+        }
+        Err(Status::with_error_details(
+            Code::InvalidArgument,
+            message,
+            details,
+        ))
+    }
+}
+
+/// Canonical stored form of an email: trimmed and lowercased, so equal addresses compare equal.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+fn is_valid_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => !local.is_empty() && domain.contains('.'),
+        None => false,
+    }
+}
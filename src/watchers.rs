@@ -0,0 +1,48 @@
+//! Caps how many `WatchNews` and `WatchAllChanges` streams may be open at once.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use tonic::Status;
+
+#[derive(Debug, Clone, Default)]
+pub struct WatcherLimit {
+    active: Arc<AtomicUsize>,
+    /// `None` allows any number of watchers.
+    max: Option<usize>,
+}
+
+impl WatcherLimit {
+    pub fn new(max: Option<usize>) -> Self {
+        Self {
+            active: Arc::default(),
+            max,
+        }
+    }
+
+    /// Reserves a slot for one stream, held until the returned guard is dropped, or fails with
+    /// `resource_exhausted` when `max` streams are already open.
+    pub fn acquire(&self) -> Result<WatcherGuard, Status> {
+        self.active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+                match self.max {
+                    Some(max) if active >= max => None,
+                    _ => Some(active + 1),
+                }
+            })
+            .map_err(|_| Status::resource_exhausted("too many open watch streams"))?;
+        Ok(WatcherGuard(self.active.clone()))
+    }
+}
+
+/// Frees its slot when dropped, i.e. when the stream holding it is closed.
+#[derive(Debug)]
+pub struct WatcherGuard(Arc<AtomicUsize>);
+
+impl Drop for WatcherGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}